@@ -0,0 +1,40 @@
+//! Using the core library without any custom extensions: `DefaultV` instantiates all extension
+//! points with the uninhabited `Never` type, and a virtual machine runs closures that call core
+//! functions.
+
+use guvm_rs::{Closure, Function, Instruction, Value, VirtualMachine};
+use vvvm_rs::{DefaultV, Fun, SynchronousFun, SynchronousCoreFunction, VvvmFailure, CoreFailure};
+
+// `(n) => int_mul(n, 2)`, with the argument in register 0.
+fn double() -> DefaultV {
+    let int_mul = DefaultV::Fun(Fun::SynchronousFunction(SynchronousFun::Core(SynchronousCoreFunction::IntMul)));
+    let function = Function {
+        asynchronous: false,
+        arity: 1,
+        register_count: 3,
+        instructions: vec![
+            Instruction::Literal { value: int_mul, dst: 1 },
+            Instruction::Literal { value: DefaultV::int(2), dst: 2 },
+            Instruction::Call { callee: 1, args: vec![0, 2], dst: 0 },
+            Instruction::Return { value: 0 },
+        ],
+    };
+    DefaultV::new_closure(Closure::new(function))
+}
+
+fn main() {
+    let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+
+    match vm.invoke(double(), &[DefaultV::int(21)]) {
+        Ok(v) => println!("double(21) = {:?}", v),
+        Err(e) => println!("{}", e),
+    }
+
+    if let Err(VvvmFailure::Core(CoreFailure::Overflow { operands, .. })) = vm.invoke(double(), &[DefaultV::int(i64::MAX)]) {
+        println!("doubling {:?} overflows", operands);
+    }
+
+    if let Err(e) = vm.invoke(double(), &[DefaultV::boo(true)]) {
+        println!("double(true) fails: {}", e);
+    }
+}
@@ -0,0 +1,26 @@
+//! Running several tasks on the built-in executor, interleaved by `preemptive_yield`.
+
+use guvm_rs::{BuiltInAsyncFunction, VirtualMachine};
+use vvvm_rs::{AsynchronousCoreFunction, AsynchronousFun, DefaultConfig, DefaultV, VvvmFailure};
+use vvvm_rs::executor::Executor;
+
+fn main() {
+    let mut executor: Executor<DefaultConfig> = Executor::new();
+
+    for name in vec!["a", "b", "c"] {
+        executor.spawn(async move {
+            let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+            for i in 0..3 {
+                println!("{}: step {}", name, i);
+                AsynchronousFun::Core(AsynchronousCoreFunction::PreemptiveYield)
+                    .invoke(&[], &mut vm)
+                    .await?;
+            }
+            Ok::<_, VvvmFailure<DefaultConfig>>(DefaultV::nil())
+        });
+    }
+
+    for (id, result) in executor.run() {
+        println!("{:?} finished with {:?}", id, result);
+    }
+}
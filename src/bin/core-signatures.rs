@@ -0,0 +1,5 @@
+//! Print the signatures of all core functions as JSON.
+
+fn main() {
+    println!("{}", vvvm_rs::signature::all_to_json());
+}
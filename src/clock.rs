@@ -0,0 +1,264 @@
+//! The source of time for the `time_*` core functions.
+//!
+//! An `Executor` consults its clock whenever none of its tasks can make progress: the clock then
+//! wakes the tasks whose timers are due next, either by actually waiting (`RealClock`) or by
+//! jumping ahead in time (`VirtualClock`), so that tests of time-based scripts run instantly and
+//! deterministically.
+
+use core::cell::{Cell, RefCell};
+use core::task::Waker;
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A monotonic clock with millisecond resolution that can wake tasks at given points in time.
+pub trait Clock {
+    /// The number of milliseconds since some fixed point in time.
+    fn now(&self) -> u64;
+
+    /// Wake `waker` once `now()` is at least `deadline`.
+    fn wake_at(&self, deadline: u64, waker: Waker) -> TimerId;
+
+    /// Remove a timer registered via `wake_at`, so that it does not wake anything. Does nothing if
+    /// the timer has fired already.
+    fn cancel(&self, timer: TimerId);
+
+    /// Called when no task can make progress. Wakes the tasks with the earliest deadline, after
+    /// waiting for it if necessary. Returns `false` if there are no timers.
+    fn advance(&self) -> bool;
+}
+
+/// Identifies a timer registered with a `Clock`, see `Clock::cancel`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimerId {
+    deadline: u64,
+    // Keeps timers with the same deadline apart.
+    id: u64,
+}
+
+// Wakers by deadline.
+#[derive(Default)]
+struct Timers {
+    timers: BTreeMap<TimerId, Waker>,
+    next_id: u64,
+}
+
+impl Timers {
+    fn insert(&mut self, deadline: u64, waker: Waker) -> TimerId {
+        let timer = TimerId { deadline, id: self.next_id };
+        self.next_id += 1;
+        self.timers.insert(timer, waker);
+        timer
+    }
+
+    fn remove(&mut self, timer: TimerId) {
+        self.timers.remove(&timer);
+    }
+
+    fn next_deadline(&self) -> Option<u64> {
+        self.timers.keys().next().map(|timer| timer.deadline)
+    }
+
+    fn wake_due(&mut self, now: u64) {
+        while let Some(timer) = self.timers.first_entry() {
+            if timer.key().deadline > now {
+                break;
+            }
+            timer.remove().wake();
+        }
+    }
+}
+
+/// The system's monotonic clock, measured from the creation of the `RealClock`.
+pub struct RealClock {
+    start: Instant,
+    timers: RefCell<Timers>,
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        RealClock {
+            start: Instant::now(),
+            timers: RefCell::new(Timers::default()),
+        }
+    }
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    fn wake_at(&self, deadline: u64, waker: Waker) -> TimerId {
+        self.timers.borrow_mut().insert(deadline, waker)
+    }
+
+    fn cancel(&self, timer: TimerId) {
+        self.timers.borrow_mut().remove(timer);
+    }
+
+    fn advance(&self) -> bool {
+        let deadline = match self.timers.borrow().next_deadline() {
+            Some(deadline) => deadline,
+            None => return false,
+        };
+
+        let now = self.now();
+        if deadline > now {
+            thread::sleep(Duration::from_millis(deadline - now));
+        }
+        self.timers.borrow_mut().wake_due(self.now());
+        true
+    }
+}
+
+/// A clock that only moves when told to, or when all tasks are waiting for timers.
+///
+/// Keep an `Rc` to it to inspect or advance the time from outside the executor.
+#[derive(Default)]
+pub struct VirtualClock {
+    now: Cell<u64>,
+    timers: RefCell<Timers>,
+}
+
+impl VirtualClock {
+    /// A clock starting at time zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the clock forward by `ms` milliseconds, waking all tasks whose timers become due.
+    pub fn advance_by(&self, ms: u64) {
+        self.now.set(self.now.get().saturating_add(ms));
+        self.timers.borrow_mut().wake_due(self.now.get());
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+
+    fn wake_at(&self, deadline: u64, waker: Waker) -> TimerId {
+        if deadline <= self.now.get() {
+            // A timer that has fired already.
+            let mut timers = self.timers.borrow_mut();
+            let timer = TimerId { deadline, id: timers.next_id };
+            timers.next_id += 1;
+            drop(timers);
+            waker.wake();
+            timer
+        } else {
+            self.timers.borrow_mut().insert(deadline, waker)
+        }
+    }
+
+    fn cancel(&self, timer: TimerId) {
+        self.timers.borrow_mut().remove(timer);
+    }
+
+    fn advance(&self) -> bool {
+        let deadline = match self.timers.borrow().next_deadline() {
+            Some(deadline) => deadline,
+            None => return false,
+        };
+
+        self.now.set(self.now.get().max(deadline));
+        self.timers.borrow_mut().wake_due(self.now.get());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use core::task::Waker;
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use super::{Clock, VirtualClock};
+
+    #[derive(Default)]
+    struct Count(AtomicUsize);
+
+    impl Wake for Count {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn counter() -> (Arc<Count>, Waker) {
+        let count = Arc::new(Count::default());
+        (count.clone(), Waker::from(count))
+    }
+
+    fn count(count: &Count) -> usize {
+        count.0.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn advance_by_wakes_due_timers() {
+        let clock = VirtualClock::new();
+        let (early, early_waker) = counter();
+        let (late, late_waker) = counter();
+        clock.wake_at(10, early_waker);
+        clock.wake_at(20, late_waker);
+
+        clock.advance_by(15);
+        assert_eq!(clock.now(), 15);
+        assert_eq!((count(&early), count(&late)), (1, 0));
+
+        clock.advance_by(5);
+        assert_eq!((count(&early), count(&late)), (1, 1));
+    }
+
+    #[test]
+    fn advance_jumps_to_the_next_deadline() {
+        let clock = VirtualClock::new();
+        let (woken, waker) = counter();
+        clock.wake_at(30, waker.clone());
+        clock.wake_at(30, waker.clone());
+        clock.wake_at(50, waker);
+
+        assert!(clock.advance());
+        assert_eq!(clock.now(), 30);
+        assert_eq!(count(&woken), 2);
+
+        assert!(clock.advance());
+        assert_eq!(clock.now(), 50);
+        assert!(!clock.advance());
+        assert_eq!(clock.now(), 50);
+    }
+
+    #[test]
+    fn past_deadlines_wake_immediately() {
+        let clock = VirtualClock::new();
+        clock.advance_by(10);
+        let (woken, waker) = counter();
+        let timer = clock.wake_at(5, waker);
+        assert_eq!(count(&woken), 1);
+
+        clock.cancel(timer);
+        assert!(!clock.advance());
+    }
+
+    #[test]
+    fn cancelled_timers_do_not_wake() {
+        let clock = VirtualClock::new();
+        let (woken, waker) = counter();
+        let timer = clock.wake_at(10, waker.clone());
+        clock.wake_at(20, waker);
+        clock.cancel(timer);
+
+        assert!(clock.advance());
+        assert_eq!(clock.now(), 20);
+        assert_eq!(count(&woken), 1);
+        assert!(!clock.advance());
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+
+use gc_immutable_collections::Map;
+
+use crate::{V, VvvmConfig, CoreFailure};
+use crate::fun::util::as_array;
+use crate::signature::Type;
+
+/// Rust types that can be extracted from a value.
+pub trait FromValue<C: VvvmConfig>: Sized {
+    fn from_value(v: &V<C>) -> Result<Self, CoreFailure<V<C>>>;
+}
+
+/// Rust types that can be turned into a value.
+pub trait IntoValue<C: VvvmConfig> {
+    fn into_value(self) -> V<C>;
+}
+
+impl<C: VvvmConfig> FromValue<C> for V<C> {
+    fn from_value(v: &V<C>) -> Result<Self, CoreFailure<V<C>>> {
+        Ok(v.clone())
+    }
+}
+
+impl<C: VvvmConfig> IntoValue<C> for V<C> {
+    fn into_value(self) -> V<C> {
+        self
+    }
+}
+
+impl<C: VvvmConfig> FromValue<C> for () {
+    fn from_value(_: &V<C>) -> Result<Self, CoreFailure<V<C>>> {
+        Ok(())
+    }
+}
+
+impl<C: VvvmConfig> IntoValue<C> for () {
+    fn into_value(self) -> V<C> {
+        V::nil()
+    }
+}
+
+impl<C: VvvmConfig> FromValue<C> for bool {
+    fn from_value(v: &V<C>) -> Result<Self, CoreFailure<V<C>>> {
+        match v {
+            V::Bool(b) => Ok(*b),
+            _ => Err(CoreFailure::wrong_type(Type::Bool, v.clone())),
+        }
+    }
+}
+
+impl<C: VvvmConfig> IntoValue<C> for bool {
+    fn into_value(self) -> V<C> {
+        V::boo(self)
+    }
+}
+
+impl<C: VvvmConfig> FromValue<C> for i64 {
+    fn from_value(v: &V<C>) -> Result<Self, CoreFailure<V<C>>> {
+        match v {
+            V::Int(n) => Ok(*n),
+            _ => Err(CoreFailure::wrong_type(Type::Int, v.clone())),
+        }
+    }
+}
+
+impl<C: VvvmConfig> IntoValue<C> for i64 {
+    fn into_value(self) -> V<C> {
+        V::int(self)
+    }
+}
+
+impl<C: VvvmConfig> FromValue<C> for f64 {
+    fn from_value(v: &V<C>) -> Result<Self, CoreFailure<V<C>>> {
+        match v {
+            V::Float(x) => Ok(x.0),
+            _ => Err(CoreFailure::wrong_type(Type::Float, v.clone())),
+        }
+    }
+}
+
+impl<C: VvvmConfig> IntoValue<C> for f64 {
+    fn into_value(self) -> V<C> {
+        V::float(self)
+    }
+}
+
+/// `None` corresponds to `nil`, everything else to `Some`.
+///
+/// Nested options are ambiguous: both `None` and `Some(None)` correspond to `nil`, which converts
+/// back to `None`.
+impl<T, C: VvvmConfig> FromValue<C> for Option<T> where
+    T: FromValue<C>,
+{
+    fn from_value(v: &V<C>) -> Result<Self, CoreFailure<V<C>>> {
+        match v {
+            V::Nil => Ok(None),
+            _ => T::from_value(v).map(Some),
+        }
+    }
+}
+
+impl<T, C: VvvmConfig> IntoValue<C> for Option<T> where
+    T: IntoValue<C>,
+{
+    fn into_value(self) -> V<C> {
+        match self {
+            None => V::nil(),
+            Some(t) => t.into_value(),
+        }
+    }
+}
+
+impl<T, C: VvvmConfig> FromValue<C> for Vec<T> where
+    T: FromValue<C>,
+{
+    fn from_value(v: &V<C>) -> Result<Self, CoreFailure<V<C>>> {
+        as_array(v)?.iter().map(T::from_value).collect()
+    }
+}
+
+impl<T, C: VvvmConfig> IntoValue<C> for Vec<T> where
+    T: IntoValue<C>,
+{
+    fn into_value(self) -> V<C> {
+        V::Array(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl<K, T, C: VvvmConfig> FromValue<C> for BTreeMap<K, T> where
+    K: FromValue<C> + Ord,
+    T: FromValue<C>,
+{
+    fn from_value(v: &V<C>) -> Result<Self, CoreFailure<V<C>>> {
+        as_map(v)?
+            .iter()
+            .map(|(key, value)| Ok((K::from_value(key)?, T::from_value(value)?)))
+            .collect()
+    }
+}
+
+impl<K, T, C: VvvmConfig> IntoValue<C> for BTreeMap<K, T> where
+    K: IntoValue<C>,
+    T: IntoValue<C>,
+{
+    fn into_value(self) -> V<C> {
+        V::Map(self.into_iter().map(|(key, value)| (key.into_value(), value.into_value())).collect())
+    }
+}
+
+// Tuples correspond to arrays of the same length.
+macro_rules! tuple_conversions {
+    ($len:expr; $($T:ident $i:tt),+) => (
+        impl<$($T,)+ C: VvvmConfig> FromValue<C> for ($($T,)+) where
+            $($T: FromValue<C>,)+
+        {
+            fn from_value(v: &V<C>) -> Result<Self, CoreFailure<V<C>>> {
+                let elements = elements(v, $len)?;
+                Ok(($($T::from_value(elements[$i])?,)+))
+            }
+        }
+
+        impl<$($T,)+ C: VvvmConfig> IntoValue<C> for ($($T,)+) where
+            $($T: IntoValue<C>,)+
+        {
+            fn into_value(self) -> V<C> {
+                V::Array(vec![$(self.$i.into_value()),+].into_iter().collect())
+            }
+        }
+    );
+}
+
+tuple_conversions!(1; A0 0);
+tuple_conversions!(2; A0 0, A1 1);
+tuple_conversions!(3; A0 0, A1 1, A2 2);
+tuple_conversions!(4; A0 0, A1 1, A2 2, A3 3);
+tuple_conversions!(5; A0 0, A1 1, A2 2, A3 3, A4 4);
+tuple_conversions!(6; A0 0, A1 1, A2 2, A3 3, A4 4, A5 5);
+tuple_conversions!(7; A0 0, A1 1, A2 2, A3 3, A4 4, A5 5, A6 6);
+tuple_conversions!(8; A0 0, A1 1, A2 2, A3 3, A4 4, A5 5, A6 6, A7 7);
+
+// The following helpers are used by the code generated by `#[derive(FromValue, IntoValue)]`.
+
+pub fn as_map<C: VvvmConfig>(v: &V<C>) -> Result<&Map<V<C>, V<C>>, CoreFailure<V<C>>> {
+    match v {
+        V::Map(m) => Ok(m),
+        _ => Err(CoreFailure::wrong_type(Type::Map(&Type::Any, &Type::Any), v.clone())),
+    }
+}
+
+/// The elements of an array that must have exactly `len` elements.
+pub fn elements<C: VvvmConfig>(v: &V<C>, len: usize) -> Result<Vec<&V<C>>, CoreFailure<V<C>>> {
+    let elements: Vec<_> = as_array(v)?.iter().collect();
+    if elements.len() == len {
+        Ok(elements)
+    } else {
+        Err(CoreFailure::wrong_length(len, v.clone()))
+    }
+}
+
+/// Extract the entry of the given name from a map, failing with `CoreFailure::MissingKey` if there
+/// is none.
+pub fn field<T, C: VvvmConfig>(v: &V<C>, name: &str) -> Result<T, CoreFailure<V<C>>> where
+    T: FromValue<C>,
+{
+    let key = V::string(name);
+    match as_map(v)?.get(&key) {
+        Some(entry) => T::from_value(entry),
+        None => Err(CoreFailure::missing_key(key, v.clone())),
+    }
+}
+
+/// Split a tagged value, i.e. a map with exactly one entry, into its tag and payload.
+pub fn untag<C: VvvmConfig>(v: &V<C>) -> Result<(&V<C>, &V<C>), CoreFailure<V<C>>> {
+    let m = as_map(v)?;
+    let mut entries = m.iter();
+    match (entries.next(), entries.next()) {
+        (Some(entry), None) => Ok(entry),
+        _ => Err(CoreFailure::wrong_length(1, v.clone())),
+    }
+}
+
+/// Create a tagged value, i.e. a map with a single entry from the tag to the payload.
+pub fn tag<C: VvvmConfig>(name: &str, payload: V<C>) -> V<C> {
+    V::Map(vec![(V::string(name), payload)].into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FromValue, IntoValue};
+    use crate::{V, DefaultV, CoreFailure};
+
+    #[test]
+    fn nested_options() {
+        let nested: DefaultV = Some(None::<i64>).into_value();
+        assert_eq!(nested, V::nil());
+        assert_eq!(Option::<Option<i64>>::from_value(&nested).ok(), Some(None));
+        assert_eq!(Option::<Option<i64>>::from_value(&V::int(1)).ok(), Some(Some(Some(1))));
+    }
+
+    #[test]
+    fn type_mismatches() {
+        match i64::from_value(&DefaultV::boo(true)) {
+            Err(CoreFailure::WrongType { value: V::Bool(true), .. }) => {}
+            _ => panic!("expected a wrong type"),
+        }
+        match <(i64, i64)>::from_value(&DefaultV::Array(vec![V::int(1)].into_iter().collect())) {
+            Err(CoreFailure::WrongLength { expected: 2, .. }) => {}
+            _ => panic!("expected a wrong length"),
+        }
+        match Vec::<bool>::from_value(&DefaultV::Array(vec![V::int(1)].into_iter().collect())) {
+            Err(CoreFailure::WrongType { value: V::Int(1), .. }) => {}
+            _ => panic!("expected a wrong type of an element"),
+        }
+    }
+}
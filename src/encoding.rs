@@ -0,0 +1,261 @@
+//! A compact binary encoding of values, e.g. for storing them in a `Recording`.
+//!
+//! Core functions are encoded by name. Values that cannot be reconstructed (closures, built-in
+//! functions of the configuration and opaque values) are encoded as a placeholder naming what
+//! they were, so that they can still be compared, but decoding a placeholder fails.
+
+use core::fmt;
+use std::error::Error;
+
+use crate::{V, VvvmConfig, Fun, SynchronousFun, AsynchronousFun};
+use crate::registry::CoreFunction;
+
+const NIL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const INT: u8 = 3;
+const FLOAT: u8 = 4;
+const ARRAY: u8 = 5;
+const MAP: u8 = 6;
+const CORE_FUNCTION: u8 = 7;
+const PLACEHOLDER: u8 = 8;
+
+/// Why a sequence of bytes could not be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended in the middle of a value.
+    UnexpectedEnd,
+    /// There were bytes left after the value.
+    TrailingBytes,
+    UnknownTag(u8),
+    /// A core function name that is not known to this version of the library.
+    UnknownFunction(String),
+    InvalidUtf8,
+    /// A placeholder for a value that could not be encoded.
+    Placeholder(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after the value"),
+            DecodeError::UnknownTag(tag) => write!(f, "unknown tag {}", tag),
+            DecodeError::UnknownFunction(name) => write!(f, "unknown core function {}", name),
+            DecodeError::InvalidUtf8 => write!(f, "invalid utf-8"),
+            DecodeError::Placeholder(what) => write!(f, "cannot decode a {}", what),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+pub fn encode<C: VvvmConfig>(v: &V<C>) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_into(v, &mut out);
+    out
+}
+
+pub fn decode<C: VvvmConfig>(bytes: &[u8]) -> Result<V<C>, DecodeError> {
+    let mut input = bytes;
+    let v = decode_from(&mut input)?;
+    if input.is_empty() {
+        Ok(v)
+    } else {
+        Err(DecodeError::TrailingBytes)
+    }
+}
+
+/// Whether `decode` can reconstruct `v` from its encoding, i.e. whether `v` contains no values
+/// that are encoded as placeholders.
+pub fn is_decodable<C: VvvmConfig>(v: &V<C>) -> bool {
+    match v {
+        V::Nil | V::Bool(_) | V::Int(_) | V::Float(_) => true,
+        V::Array(a) => a.iter().all(is_decodable),
+        V::Map(m) => m.iter().all(|(key, value)| is_decodable(key) && is_decodable(value)),
+        V::Fun(Fun::SynchronousFunction(SynchronousFun::Core(_)))
+        | V::Fun(Fun::AsynchronousFunction(AsynchronousFun::Core(_))) => true,
+        V::Fun(_) | V::Opaque(_) => false,
+    }
+}
+
+fn encode_into<C: VvvmConfig>(v: &V<C>, out: &mut Vec<u8>) {
+    match v {
+        V::Nil => out.push(NIL),
+        V::Bool(false) => out.push(FALSE),
+        V::Bool(true) => out.push(TRUE),
+        V::Int(n) => {
+            out.push(INT);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+        V::Float(x) => {
+            out.push(FLOAT);
+            out.extend_from_slice(&x.0.to_bits().to_be_bytes());
+        }
+        V::Array(a) => {
+            out.push(ARRAY);
+            write_length(a.len(), out);
+            for item in a.iter() {
+                encode_into(item, out);
+            }
+        }
+        V::Map(m) => {
+            out.push(MAP);
+            write_length(m.len(), out);
+            for (key, value) in m.iter() {
+                encode_into(key, out);
+                encode_into(value, out);
+            }
+        }
+        V::Fun(Fun::SynchronousFunction(SynchronousFun::Core(f))) => {
+            out.push(CORE_FUNCTION);
+            write_bytes(f.name().as_bytes(), out);
+        }
+        V::Fun(Fun::AsynchronousFunction(AsynchronousFun::Core(f))) => {
+            out.push(CORE_FUNCTION);
+            write_bytes(f.name().as_bytes(), out);
+        }
+        V::Fun(Fun::Closure(_)) => write_placeholder("closure", out),
+        V::Fun(_) => write_placeholder("built-in function", out),
+        V::Opaque(o) => write_placeholder(o.kind(), out),
+    }
+}
+
+fn write_placeholder(what: &str, out: &mut Vec<u8>) {
+    out.push(PLACEHOLDER);
+    write_bytes(what.as_bytes(), out);
+}
+
+fn decode_from<C: VvvmConfig>(input: &mut &[u8]) -> Result<V<C>, DecodeError> {
+    match read_u8(input)? {
+        NIL => Ok(V::nil()),
+        FALSE => Ok(V::boo(false)),
+        TRUE => Ok(V::boo(true)),
+        INT => Ok(V::int(read_u64(input)? as i64)),
+        FLOAT => Ok(V::float(f64::from_bits(read_u64(input)?))),
+        ARRAY => {
+            let len = read_length(input)?;
+            let mut items = Vec::new();
+            for _ in 0..len {
+                items.push(decode_from(input)?);
+            }
+            Ok(V::Array(items.into_iter().collect()))
+        }
+        MAP => {
+            let len = read_length(input)?;
+            let mut entries = Vec::new();
+            for _ in 0..len {
+                let key = decode_from(input)?;
+                entries.push((key, decode_from(input)?));
+            }
+            Ok(V::Map(entries.into_iter().collect()))
+        }
+        CORE_FUNCTION => {
+            let name = read_string(input)?;
+            match CoreFunction::from_name(&name) {
+                Some(f) => Ok(f.to_value()),
+                None => Err(DecodeError::UnknownFunction(name)),
+            }
+        }
+        PLACEHOLDER => Err(DecodeError::Placeholder(read_string(input)?)),
+        tag => Err(DecodeError::UnknownTag(tag)),
+    }
+}
+
+pub(crate) fn write_length(len: usize, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(len as u64).to_be_bytes());
+}
+
+pub(crate) fn write_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    write_length(bytes.len(), out);
+    out.extend_from_slice(bytes);
+}
+
+pub(crate) fn read_u8(input: &mut &[u8]) -> Result<u8, DecodeError> {
+    let (first, rest) = input.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+    *input = rest;
+    Ok(*first)
+}
+
+fn read_u64(input: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(read_slice(8, input)?);
+    Ok(u64::from_be_bytes(buf))
+}
+
+pub(crate) fn read_length(input: &mut &[u8]) -> Result<usize, DecodeError> {
+    let len = read_u64(input)?;
+    // Every item takes at least one byte, this guards against huge allocations.
+    if len > input.len() as u64 {
+        Err(DecodeError::UnexpectedEnd)
+    } else {
+        Ok(len as usize)
+    }
+}
+
+pub(crate) fn read_bytes<'a>(input: &mut &'a [u8]) -> Result<&'a [u8], DecodeError> {
+    let len = read_length(input)?;
+    read_slice(len, input)
+}
+
+fn read_string(input: &mut &[u8]) -> Result<String, DecodeError> {
+    String::from_utf8(read_bytes(input)?.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+}
+
+fn read_slice<'a>(len: usize, input: &mut &'a [u8]) -> Result<&'a [u8], DecodeError> {
+    if input.len() < len {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    let (slice, rest) = input.split_at(len);
+    *input = rest;
+    Ok(slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, decode, is_decodable, DecodeError};
+    use crate::{V, DefaultConfig, DefaultV, Opaque, CoreFunction, SynchronousCoreFunction};
+
+    fn array(items: Vec<DefaultV>) -> DefaultV {
+        V::Array(items.into_iter().collect())
+    }
+
+    #[test]
+    fn round_trip() {
+        let map: DefaultV = V::Map(vec![(V::int(-1), V::float(0.5)), (V::string("k"), V::nil())].into_iter().collect());
+        let values = vec![
+            V::nil(),
+            V::boo(false),
+            V::boo(true),
+            V::int(i64::MIN),
+            V::float(-2.25),
+            array(vec![]),
+            array(vec![V::int(1), array(vec![V::boo(true)]), map.clone()]),
+            map,
+            CoreFunction::from(SynchronousCoreFunction::IntAdd).to_value(),
+        ];
+
+        for v in values {
+            assert!(is_decodable(&v));
+            assert_eq!(decode::<DefaultConfig>(&encode(&v)), Ok(v));
+        }
+    }
+
+    #[test]
+    fn placeholders() {
+        let v: DefaultV = array(vec![V::int(1), V::Opaque(Opaque::new("thing", ()))]);
+        assert!(!is_decodable(&v));
+        assert_eq!(decode::<DefaultConfig>(&encode(&v)), Err(DecodeError::Placeholder("thing".to_string())));
+    }
+
+    #[test]
+    fn malformed() {
+        let bytes = encode::<DefaultConfig>(&array(vec![V::int(1)]));
+        assert_eq!(decode::<DefaultConfig>(&bytes[..bytes.len() - 1]), Err(DecodeError::UnexpectedEnd));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(decode::<DefaultConfig>(&trailing), Err(DecodeError::TrailingBytes));
+        assert_eq!(decode::<DefaultConfig>(&[42]), Err(DecodeError::UnknownTag(42)));
+    }
+}
@@ -0,0 +1,710 @@
+//! A minimal single-threaded executor for running many script tasks concurrently, without
+//! depending on an async runtime.
+
+use core::any::Any;
+use core::cell::{Cell, RefCell};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::Wake;
+
+use crate::{V, VvvmConfig, VvvmFailure, VvvmResult, CoreFailure, Opaque};
+use crate::clock::{Clock, RealClock};
+use crate::fun::util::add_waker;
+use crate::{fuel, sandbox};
+
+/// Identifies a task spawned on an `Executor`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(u64);
+
+/// A boxed future as run by the executor.
+pub type Task<C> = Pin<Box<dyn Future<Output = VvvmResult<C>>>>;
+
+/// Turns a function value and its arguments into a task, typically by running it in a fresh
+/// `VirtualMachine`. Required for the `task_spawn` core function.
+pub type Runner<C> = Rc<dyn Fn(V<C>, Vec<V<C>>) -> Task<C>>;
+
+/// Runs futures (typically each driving its own `VirtualMachine`) on the current thread.
+///
+/// Tasks are polled in the order in which they are woken, so a task that yields via
+/// `preemptive_yield` lets all other runnable tasks run before it continues. Once no task is
+/// runnable, the executor's `Clock` wakes the tasks whose timers are due next.
+///
+/// The executor keeps the results of the tasks spawned via `spawn` until the host takes them,
+/// and forgets tasks spawned by scripts once they have finished and no handle to them remains.
+pub struct Executor<C: VvvmConfig> {
+    shared: Rc<Shared<C>>,
+}
+
+pub(crate) struct Shared<C: VvvmConfig> {
+    tasks: RefCell<BTreeMap<TaskId, TaskState<C>>>,
+    next_id: Cell<u64>,
+    queue: Arc<Mutex<VecDeque<TaskId>>>,
+    runner: RefCell<Option<Runner<C>>>,
+    clock: RefCell<Rc<dyn Clock>>,
+}
+
+struct TaskState<C: VvvmConfig> {
+    // `None` while the task is being polled and once it has finished.
+    future: Option<Task<C>>,
+    waker: Arc<TaskWaker>,
+    result: Option<VvvmResult<C>>,
+    // Wakers of the tasks waiting for this one to finish.
+    joiners: Vec<Waker>,
+    parent: Option<TaskId>,
+    children: Vec<TaskId>,
+    // Results of tasks spawned by the host are handed out by `Executor::run`.
+    spawned_by_host: bool,
+    // Whether all handles to the task are gone, so that it can be removed once it finishes.
+    released: bool,
+}
+
+impl<C: VvvmConfig> Executor<C> {
+    pub fn new() -> Self {
+        let clock: Rc<dyn Clock> = Rc::new(RealClock::new());
+        Executor {
+            shared: Rc::new(Shared {
+                tasks: RefCell::new(BTreeMap::new()),
+                next_id: Cell::new(0),
+                queue: Arc::new(Mutex::new(VecDeque::new())),
+                runner: RefCell::new(None),
+                clock: RefCell::new(clock),
+            }),
+        }
+    }
+
+    /// Set the function with which `task_spawn` turns closures into tasks.
+    pub fn set_runner<F>(&mut self, runner: F) where
+        F: Fn(V<C>, Vec<V<C>>) -> Task<C> + 'static,
+    {
+        *self.shared.runner.borrow_mut() = Some(Rc::new(runner));
+    }
+
+    /// Set the clock used by the `time_*` core functions, a `RealClock` by default.
+    pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        *self.shared.clock.borrow_mut() = clock;
+    }
+
+    /// Add a task, it is first polled by the next call to `run` or `run_until_stalled`.
+    pub fn spawn<F>(&mut self, f: F) -> TaskId where
+        F: Future<Output = VvvmResult<C>> + 'static,
+    {
+        self.shared.spawn(Box::pin(f), None, true)
+    }
+
+    /// Cancel a task and, transitively, all tasks it spawned.
+    pub fn cancel(&mut self, id: TaskId) {
+        self.shared.cancel(id)
+    }
+
+    /// Poll tasks until none of them can make progress, advancing the clock whenever all tasks
+    /// are waiting. Returns whether any tasks are still unfinished, i.e. waiting for a wakeup that
+    /// only something outside the executor can cause.
+    pub fn run_until_stalled(&mut self) -> bool {
+        loop {
+            let next = self.shared.queue.lock().unwrap().pop_front();
+            let id = match next {
+                Some(id) => id,
+                None => {
+                    if self.pending() > 0 && self.shared.clock().advance() {
+                        continue;
+                    }
+                    return self.pending() > 0;
+                }
+            };
+
+            let taken = match self.shared.tasks.borrow_mut().get_mut(&id) {
+                Some(task) => task.future.take().map(|future| (future, task.waker.clone())),
+                None => None,
+            };
+            // The task has finished or been cancelled since it was woken.
+            let (mut future, waker) = match taken {
+                Some(taken) => taken,
+                None => continue,
+            };
+
+            waker.queued.store(false, Ordering::SeqCst);
+            let waker = Waker::from(waker);
+            let mut cx = Context::from_waker(&waker);
+            let poll = {
+                let _current = CurrentGuard::enter(self.shared.clone(), id);
+                future.as_mut().poll(&mut cx)
+            };
+
+            match poll {
+                Poll::Ready(result) => self.shared.finish(id, result),
+                Poll::Pending => {
+                    let mut tasks = self.shared.tasks.borrow_mut();
+                    if let Some(task) = tasks.get_mut(&id) {
+                        // Unless the task was cancelled while it was being polled.
+                        if task.result.is_none() {
+                            task.future = Some(future);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Run until no task can make progress, and return the results of all tasks spawned via
+    /// `spawn` that have finished since the last call.
+    pub fn run(&mut self) -> BTreeMap<TaskId, VvvmResult<C>> {
+        self.run_until_stalled();
+
+        let finished: Vec<TaskId> = self.shared.tasks
+            .borrow()
+            .iter()
+            .filter(|(_, task)| task.spawned_by_host && task.result.is_some())
+            .map(|(id, _)| *id)
+            .collect();
+        finished
+            .into_iter()
+            .filter_map(|id| self.shared.remove(id).and_then(|task| task.result).map(|result| (id, result)))
+            .collect()
+    }
+
+    /// Remove and return the result of a finished task.
+    pub fn take_result(&mut self, id: TaskId) -> Option<VvvmResult<C>> {
+        let finished = self.shared.tasks.borrow().get(&id).map_or(false, |task| task.result.is_some());
+        if finished {
+            self.shared.remove(id).and_then(|task| task.result)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_finished(&self, id: TaskId) -> bool {
+        self.shared.tasks.borrow().get(&id).map_or(true, |task| task.result.is_some())
+    }
+
+    /// The number of tasks that have not finished yet.
+    pub fn pending(&self) -> usize {
+        self.shared.tasks.borrow().values().filter(|task| task.result.is_none()).count()
+    }
+
+    /// The number of tasks the executor keeps track of: the unfinished ones, those whose results
+    /// the host has not taken yet, and finished ones to which scripts still hold a handle.
+    pub fn len(&self) -> usize {
+        self.shared.tasks.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+impl<C: VvvmConfig> Executor<C> {
+    /// The number of wakers of tasks waiting for other tasks to finish.
+    pub(crate) fn joiners(&self) -> usize {
+        self.shared.tasks.borrow().values().map(|task| task.joiners.len()).sum()
+    }
+}
+
+impl<C: VvvmConfig> Default for Executor<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: VvvmConfig> Shared<C> {
+    fn spawn(&self, future: Task<C>, parent: Option<TaskId>, spawned_by_host: bool) -> TaskId {
+        let id = TaskId(self.next_id.get());
+        self.next_id.set(self.next_id.get() + 1);
+
+        let waker = Arc::new(TaskWaker {
+            id,
+            queued: AtomicBool::new(false),
+            queue: self.queue.clone(),
+        });
+        waker.wake_by_ref();
+
+        let mut tasks = self.tasks.borrow_mut();
+        tasks.insert(id, TaskState {
+            future: Some(future),
+            waker,
+            result: None,
+            joiners: Vec::new(),
+            parent,
+            children: Vec::new(),
+            spawned_by_host,
+            released: false,
+        });
+        if let Some(parent) = parent.and_then(|parent| tasks.get_mut(&parent)) {
+            parent.children.push(id);
+        }
+        id
+    }
+
+    fn clock(&self) -> Rc<dyn Clock> {
+        self.clock.borrow().clone()
+    }
+
+    fn finish(&self, id: TaskId, result: VvvmResult<C>) {
+        let (joiners, released) = match self.tasks.borrow_mut().get_mut(&id) {
+            Some(task) if task.result.is_none() => {
+                task.result = Some(result);
+                (core::mem::replace(&mut task.joiners, Vec::new()), task.released)
+            }
+            _ => (Vec::new(), false),
+        };
+        for joiner in joiners {
+            joiner.wake();
+        }
+        if released {
+            self.remove(id);
+        }
+    }
+
+    // Called once the last handle to a task is gone: nobody can ask for its outcome anymore, so
+    // it is removed as soon as it has finished.
+    fn release(&self, id: TaskId) {
+        let finished = match self.tasks.borrow_mut().get_mut(&id) {
+            Some(task) => {
+                task.released = true;
+                task.result.is_some()
+            }
+            None => false,
+        };
+        if finished {
+            self.remove(id);
+        }
+    }
+
+    // Forget a task, handing its children to its parent so that cancelling the parent still
+    // reaches them. The caller must drop the returned state only once the tasks are no longer
+    // borrowed, since it may contain task handles.
+    fn remove(&self, id: TaskId) -> Option<TaskState<C>> {
+        let mut tasks = self.tasks.borrow_mut();
+        let task = tasks.remove(&id)?;
+        for child in task.children.iter() {
+            if let Some(child) = tasks.get_mut(child) {
+                child.parent = task.parent;
+            }
+        }
+        if let Some(parent) = task.parent.and_then(|parent| tasks.get_mut(&parent)) {
+            parent.children.retain(|child| *child != id);
+            parent.children.extend(task.children.iter().cloned());
+        }
+        Some(task)
+    }
+
+    fn cancel(&self, id: TaskId) {
+        let mut pending = vec![id];
+        // Dropped only once the tasks are no longer borrowed, since dropping a future may access
+        // the executor.
+        let mut dropped = Vec::new();
+
+        while let Some(id) = pending.pop() {
+            let mut tasks = self.tasks.borrow_mut();
+            if let Some(task) = tasks.get_mut(&id) {
+                pending.extend(task.children.iter().cloned());
+                if task.result.is_none() {
+                    dropped.extend(task.future.take());
+                    drop(tasks);
+                    self.finish(id, Err(VvvmFailure::Core(CoreFailure::cancelled())));
+                }
+            }
+        }
+
+        drop(dropped);
+    }
+
+    /// The outcome of a finished task: its value, or its failure as a value.
+    fn outcome(&self, id: TaskId) -> Option<Result<V<C>, V<C>>> {
+        self.tasks.borrow().get(&id).and_then(|task| match &task.result {
+            None => None,
+            Some(Ok(v)) => Some(Ok(v.clone())),
+            Some(Err(e)) => Some(Err(e.to_value())),
+        })
+    }
+}
+
+struct TaskWaker {
+    id: TaskId,
+    // Whether the task is in the queue already, so that repeated wakeups don't poll it repeatedly.
+    queued: AtomicBool,
+    queue: Arc<Mutex<VecDeque<TaskId>>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.queued.swap(true, Ordering::SeqCst) {
+            self.queue.lock().unwrap().push_back(self.id);
+        }
+    }
+}
+
+thread_local! {
+    // The executor and task currently being polled, the executor is a `Shared<C>`.
+    static CURRENT: RefCell<Option<(Rc<dyn Any>, TaskId)>> = RefCell::new(None);
+}
+
+struct CurrentGuard {
+    previous: Option<(Rc<dyn Any>, TaskId)>,
+}
+
+impl CurrentGuard {
+    fn enter<C: VvvmConfig>(shared: Rc<Shared<C>>, id: TaskId) -> Self {
+        let shared: Rc<dyn Any> = shared;
+        let previous = CURRENT.with(|current| current.replace(Some((shared, id))));
+        CurrentGuard { previous }
+    }
+}
+
+impl Drop for CurrentGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| current.replace(previous));
+    }
+}
+
+fn current<C: VvvmConfig>() -> Option<(Rc<Shared<C>>, TaskId)> {
+    CURRENT.with(|current| {
+        let current = current.borrow();
+        let (shared, id) = current.as_ref()?;
+        Some((shared.clone().downcast::<Shared<C>>().ok()?, *id))
+    })
+}
+
+// The payload of the opaque values that scripts use as task handles. The executor keeps the
+// outcome of a finished task only while a handle to it exists. Like the tasks' futures, outcomes
+// are not traced, see `Opaque`.
+struct TaskHandle<C: VvvmConfig> {
+    shared: Weak<Shared<C>>,
+    id: TaskId,
+}
+
+impl<C: VvvmConfig> Drop for TaskHandle<C> {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.upgrade() {
+            shared.release(self.id);
+        }
+    }
+}
+
+pub(crate) const TASK: &str = "task";
+
+pub(crate) fn as_task_handle<C: VvvmConfig>(v: &V<C>) -> Option<(Weak<Shared<C>>, TaskId)> {
+    match v {
+        V::Opaque(o) => o.downcast_ref::<TaskHandle<C>>().map(|handle| (handle.shared.clone(), handle.id)),
+        _ => None,
+    }
+}
+
+/// The clock of the executor running the current task.
+pub(crate) fn clock<C: VvvmConfig>() -> Result<Rc<dyn Clock>, CoreFailure<V<C>>> {
+    let (shared, _) = current::<C>().ok_or_else(CoreFailure::no_executor)?;
+    Ok(shared.clock())
+}
+
+/// Spawn a task running `f` as a child of the current task, and return its handle. The child is
+/// subject to the `Sandbox` and the `Fuel` active in the current task, so that scripts cannot
+/// escape either by spawning.
+pub(crate) fn spawn_child<C: VvvmConfig>(f: V<C>, args: Vec<V<C>>) -> Result<V<C>, CoreFailure<V<C>>> {
+    let (shared, parent) = current::<C>().ok_or_else(CoreFailure::no_executor)?;
+    let runner = shared.runner.borrow().clone().ok_or_else(CoreFailure::no_executor)?;
+
+    let mut task = runner(f, args);
+    if let Some(sandbox) = sandbox::active() {
+        task = Box::pin(sandbox.confine(task));
+    }
+    if let Some(fuel) = fuel::active() {
+        task = Box::pin(fuel.meter(task));
+    }
+    let id = shared.spawn(task, Some(parent), false);
+    Ok(V::Opaque(Opaque::new(TASK, TaskHandle {
+        shared: Rc::downgrade(&shared),
+        id,
+    })))
+}
+
+pub(crate) fn cancel<C: VvvmConfig>(shared: &Weak<Shared<C>>, id: TaskId) {
+    if let Some(shared) = shared.upgrade() {
+        shared.cancel(id);
+    }
+}
+
+/// Resolves to the outcome of a task, see `Shared::outcome`.
+pub(crate) struct Join<C: VvvmConfig> {
+    // Keeps the task's outcome around until it has been read.
+    handle: V<C>,
+}
+
+// Not self-referential.
+impl<C: VvvmConfig> Unpin for Join<C> {}
+
+impl<C: VvvmConfig> Join<C> {
+    /// `None` if `handle` is not a task handle.
+    pub(crate) fn new(handle: V<C>) -> Option<Self> {
+        as_task_handle(&handle)?;
+        Some(Join { handle })
+    }
+
+    /// Cancel the joined task, see `Executor::cancel`.
+    pub(crate) fn cancel(&self) {
+        let (shared, id) = as_task_handle(&self.handle).expect("checked on creation");
+        cancel(&shared, id);
+    }
+}
+
+impl<C: VvvmConfig> Future for Join<C> {
+    type Output = Result<V<C>, V<C>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let (shared, id) = as_task_handle(&self.handle).expect("checked on creation");
+        let shared = match shared.upgrade() {
+            Some(shared) => shared,
+            // The executor is gone, so the task will never finish.
+            None => return Poll::Ready(Err(CoreFailure::<V<C>>::cancelled().to_value())),
+        };
+
+        if let Some(outcome) = shared.outcome(id) {
+            return Poll::Ready(outcome);
+        }
+
+        match shared.tasks.borrow_mut().get_mut(&id) {
+            Some(task) => {
+                add_waker(&mut task.joiners, cx.waker());
+                Poll::Pending
+            }
+            // The host has already taken the result.
+            None => Poll::Ready(Err(CoreFailure::<V<C>>::cancelled().to_value())),
+        }
+    }
+}
+
+/// The outcome of a task as scripts see it: `ok(v)` or `err(failure)`.
+pub(crate) fn outcome_to_value<C: VvvmConfig>(outcome: Result<V<C>, V<C>>) -> V<C> {
+    match outcome {
+        Ok(v) => V::ok(v),
+        Err(e) => V::err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+    use core::future::{pending, poll_fn, Future};
+    use core::pin::Pin;
+    use core::task::Poll;
+    use std::rc::Rc;
+
+    use guvm_rs::{BuiltInAsyncFunction, BuiltInSynchronousFunction, VirtualMachine};
+
+    use super::{Executor, Join, Task, spawn_child, outcome_to_value};
+    use crate::{V, DefaultConfig, DefaultV, VvvmFailure, VvvmResult, CoreFailure, Fuel, Sandbox};
+    use crate::{SynchronousCoreFunction, AsynchronousCoreFunction};
+
+    fn array(items: Vec<DefaultV>) -> DefaultV {
+        V::Array(items.into_iter().collect())
+    }
+
+    #[test]
+    fn runs_to_completion() {
+        let mut executor: Executor<DefaultConfig> = Executor::new();
+        let first = executor.spawn(async { Ok(V::int(1)) });
+        let second = executor.spawn(async { Ok(V::int(2)) });
+        assert_eq!(executor.pending(), 2);
+        assert!(!executor.is_finished(first));
+
+        let mut results = executor.run();
+        assert_eq!(executor.pending(), 0);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.remove(&first).unwrap().ok(), Some(V::int(1)));
+        assert_eq!(results.remove(&second).unwrap().ok(), Some(V::int(2)));
+    }
+
+    // Logs `name` three times, invoking `preemptive_yield` in between.
+    fn yielding(name: &'static str, log: Rc<RefCell<Vec<DefaultV>>>) -> impl Future<Output = VvvmResult<DefaultConfig>> {
+        async move {
+            let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+            for i in 0..3 {
+                log.borrow_mut().push(array(vec![V::string(name), V::int(i)]));
+                let mut yield_ = AsynchronousCoreFunction::PreemptiveYield;
+                BuiltInAsyncFunction::<DefaultV, VvvmFailure<DefaultConfig>, _>::invoke(&mut yield_, &[], &mut vm).await?;
+            }
+            Ok(V::nil())
+        }
+    }
+
+    #[test]
+    fn preemptive_yield_interleaves_tasks() {
+        let mut executor: Executor<DefaultConfig> = Executor::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        executor.spawn(yielding("a", log.clone()));
+        executor.spawn(yielding("b", log.clone()));
+        assert!(executor.run().values().all(|result| result.is_ok()));
+
+        let expected: Vec<DefaultV> = (0..3)
+            .flat_map(|i| vec![array(vec![V::string("a"), V::int(i)]), array(vec![V::string("b"), V::int(i)])])
+            .collect();
+        assert_eq!(*log.borrow(), expected);
+    }
+
+    #[test]
+    fn failures_are_reported_per_task() {
+        let mut executor: Executor<DefaultConfig> = Executor::new();
+        let failing = executor.spawn(async { Err(VvvmFailure::Core(CoreFailure::halt(V::int(1)))) });
+        let succeeding = executor.spawn(async { Ok(V::int(2)) });
+
+        executor.run_until_stalled();
+        assert!(executor.is_finished(failing) && executor.is_finished(succeeding));
+        match executor.take_result(failing) {
+            Some(Err(VvvmFailure::Core(CoreFailure::Halt { value, .. }))) => assert_eq!(value, V::int(1)),
+            _ => panic!("expected the halt"),
+        }
+        assert_eq!(executor.take_result(succeeding).unwrap().ok(), Some(V::int(2)));
+        assert!(executor.take_result(failing).is_none());
+    }
+
+    // Tasks of arrays spawn a child for each item and then wait forever, all other tasks finish
+    // with the value they were spawned for.
+    fn run(f: DefaultV, _args: Vec<DefaultV>) -> Task<DefaultConfig> {
+        match f {
+            V::Array(items) => Box::pin(async move {
+                let _children: Vec<DefaultV> = items
+                    .iter()
+                    .map(|item| spawn_child(item.clone(), Vec::new()).unwrap())
+                    .collect();
+                pending::<VvvmResult<DefaultConfig>>().await
+            }),
+            f => Box::pin(async move { Ok(f) }),
+        }
+    }
+
+    fn executor() -> Executor<DefaultConfig> {
+        let mut executor = Executor::new();
+        executor.set_runner(run);
+        executor
+    }
+
+    #[test]
+    fn spawn_join_cancel() {
+        let mut executor = executor();
+        let id = executor.spawn(async {
+            let finishing = spawn_child(V::int(1), Vec::new()).unwrap();
+            let waiting = spawn_child(array(vec![]), Vec::new()).unwrap();
+            let waiting = Join::new(waiting).unwrap();
+            waiting.cancel();
+            let finished = Join::new(finishing).unwrap().await;
+            Ok(array(vec![outcome_to_value(finished), outcome_to_value(waiting.await)]))
+        });
+
+        let results = executor.run();
+        let cancelled = CoreFailure::<DefaultV>::cancelled().to_value();
+        assert_eq!(results[&id].as_ref().ok(), Some(&array(vec![V::ok(V::int(1)), V::err(cancelled)])));
+        assert!(executor.is_empty());
+    }
+
+    #[test]
+    fn cancel_cascades_to_children() {
+        let mut executor = executor();
+        let id = executor.spawn(run(array(vec![array(vec![array(vec![])]), array(vec![])]), Vec::new()));
+
+        assert!(executor.run_until_stalled());
+        assert_eq!(executor.pending(), 4);
+
+        executor.cancel(id);
+        assert_eq!(executor.pending(), 0);
+        assert!(executor.run()[&id].is_err());
+        assert!(executor.is_empty());
+    }
+
+    #[test]
+    fn finished_children_are_forgotten() {
+        let mut executor = executor();
+        let id = executor.spawn(async {
+            for i in 0..1000 {
+                let handle = spawn_child(V::int(i), Vec::new()).unwrap();
+                if i % 2 == 0 {
+                    Join::new(handle).unwrap().await.unwrap();
+                }
+            }
+            Ok(V::nil())
+        });
+
+        executor.run_until_stalled();
+        assert_eq!(executor.len(), 1);
+        assert!(executor.take_result(id).unwrap().is_ok());
+        assert!(executor.is_empty());
+    }
+
+    #[test]
+    fn repeated_polls_register_one_joiner() {
+        let mut executor = executor();
+        executor.spawn(async {
+            let mut join = Join::new(spawn_child(array(vec![]), Vec::new())?).unwrap();
+            for _ in 0..10 {
+                poll_fn(|cx| {
+                    assert!(Pin::new(&mut join).poll(cx).is_pending());
+                    Poll::Ready(())
+                }).await;
+            }
+            pending::<VvvmResult<DefaultConfig>>().await
+        });
+
+        assert!(executor.run_until_stalled());
+        assert_eq!(executor.joiners(), 1);
+    }
+
+    // Adds numbers until that fails, or gives up after many iterations.
+    fn spin(_f: DefaultV, _args: Vec<DefaultV>) -> Task<DefaultConfig> {
+        Box::pin(async {
+            let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+            for _ in 0..100_000 {
+                if let Err(e) = SynchronousCoreFunction::IntAdd.invoke(&[V::int(1), V::int(2)], &mut vm) {
+                    return Err(VvvmFailure::Core(e));
+                }
+            }
+            Ok(V::nil())
+        })
+    }
+
+    fn spin_in_child(executor: &mut Executor<DefaultConfig>, limit: impl FnOnce(Task<DefaultConfig>) -> Task<DefaultConfig>) -> DefaultV {
+        executor.set_runner(spin);
+        let id = executor.spawn(limit(Box::pin(async {
+            let child = spawn_child(V::nil(), Vec::new())?;
+            Ok(outcome_to_value(Join::new(child).unwrap().await))
+        })));
+        executor.run().remove(&id).unwrap().ok().unwrap()
+    }
+
+    fn failure_kind(outcome: &DefaultV) -> DefaultV {
+        match outcome {
+            V::Map(m) => m.get(&V::string("err")).and_then(|e| match e {
+                V::Map(e) => e.get(&V::string("kind")).cloned(),
+                _ => None,
+            }).unwrap_or_else(V::nil),
+            _ => V::nil(),
+        }
+    }
+
+    #[test]
+    fn children_share_the_fuel() {
+        let mut executor = Executor::new();
+        let fuel = Fuel::new(100);
+        let limit = fuel.clone();
+        let outcome = spin_in_child(&mut executor, move |task| Box::pin(limit.meter(task)));
+
+        assert_eq!(failure_kind(&outcome), V::string("out_of_fuel"));
+        assert_eq!(fuel.remaining(), 0);
+        assert_eq!(fuel.consumed(), 100);
+    }
+
+    #[test]
+    fn children_inherit_the_sandbox() {
+        let mut executor = Executor::new();
+        let sandbox = Sandbox::deny_list(vec![SynchronousCoreFunction::IntAdd.into()]);
+        let outcome = spin_in_child(&mut executor, move |task| Box::pin(sandbox.confine(task)));
+        assert_eq!(failure_kind(&outcome), V::string("disallowed"));
+    }
+}
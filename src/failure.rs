@@ -0,0 +1,490 @@
+use core::fmt;
+use std::error::Error;
+
+use guvm_rs::Arity;
+
+use crate::{V, VvvmConfig, VvvmFailure, IntoValue};
+use crate::registry::CoreFunction;
+use crate::signature::Type;
+
+/// Where a `CoreFailure` occurred: in which core function, and which of its arguments caused it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Location {
+    pub function: Option<CoreFunction>,
+    /// Zero-based index of the argument.
+    pub arg: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum CoreFailure<Val> {
+    /// `value_halt` was called with the given value.
+    Halt {
+        at: Location,
+        value: Val,
+    },
+    /// A value (an argument or a part of one) does not have the expected type.
+    WrongType {
+        at: Location,
+        expected: Type,
+        value: Val,
+    },
+    /// An array or map does not have the expected number of entries.
+    WrongLength {
+        at: Location,
+        expected: usize,
+        value: Val,
+    },
+    /// A map lacks an entry for `key`.
+    MissingKey {
+        at: Location,
+        key: Val,
+        value: Val,
+    },
+    /// A tagged value whose tag does not name any variant.
+    UnknownVariant {
+        at: Location,
+        value: Val,
+    },
+    /// A mutex was unlocked that is not locked.
+    NotLocked {
+        at: Location,
+        value: Val,
+    },
+    /// An integer operation on the given operands overflowed.
+    Overflow {
+        at: Location,
+        operands: Vec<i64>,
+    },
+    /// A function was invoked with the wrong number of arguments.
+    ArityMismatch {
+        at: Location,
+        expected: Arity,
+        actual: usize,
+    },
+    /// The function is not allowed by the active `Sandbox`.
+    Disallowed {
+        at: Location,
+    },
+    /// The task was cancelled before it finished.
+    Cancelled {
+        at: Location,
+    },
+    /// The function needs to run inside an `Executor` that can run closures.
+    NoExecutor {
+        at: Location,
+    },
+    /// A replayed run invoked asynchronous functions differently from the recorded one, see
+    /// `Recorder`.
+    Diverged {
+        at: Location,
+        reason: String,
+    },
+    /// A `Recorder` cannot record the invocation in a way that could be replayed.
+    Unrecordable {
+        at: Location,
+        reason: String,
+    },
+    /// There was not enough `Fuel` left to invoke the function.
+    OutOfFuel {
+        at: Location,
+        needed: u64,
+        remaining: u64,
+    },
+    /// A failure reproduced from a recording, `value` is the recorded failure as a value.
+    Recorded {
+        at: Location,
+        value: Val,
+    },
+}
+
+impl<Val> CoreFailure<Val> {
+    pub fn halt(value: Val) -> Self {
+        CoreFailure::Halt { at: Location::default(), value }
+    }
+
+    pub fn wrong_type(expected: Type, value: Val) -> Self {
+        CoreFailure::WrongType { at: Location::default(), expected, value }
+    }
+
+    pub fn wrong_length(expected: usize, value: Val) -> Self {
+        CoreFailure::WrongLength { at: Location::default(), expected, value }
+    }
+
+    pub fn missing_key(key: Val, value: Val) -> Self {
+        CoreFailure::MissingKey { at: Location::default(), key, value }
+    }
+
+    pub fn unknown_variant(value: Val) -> Self {
+        CoreFailure::UnknownVariant { at: Location::default(), value }
+    }
+
+    pub fn not_locked(value: Val) -> Self {
+        CoreFailure::NotLocked { at: Location::default(), value }
+    }
+
+    pub fn overflow(operands: Vec<i64>) -> Self {
+        CoreFailure::Overflow { at: Location::default(), operands }
+    }
+
+    pub fn arity_mismatch(expected: Arity, actual: usize) -> Self {
+        CoreFailure::ArityMismatch { at: Location::default(), expected, actual }
+    }
+
+    pub fn disallowed(function: CoreFunction) -> Self {
+        CoreFailure::Disallowed {
+            at: Location {
+                function: Some(function),
+                arg: None,
+            },
+        }
+    }
+
+    pub fn cancelled() -> Self {
+        CoreFailure::Cancelled { at: Location::default() }
+    }
+
+    pub fn no_executor() -> Self {
+        CoreFailure::NoExecutor { at: Location::default() }
+    }
+
+    pub fn diverged(reason: String) -> Self {
+        CoreFailure::Diverged { at: Location::default(), reason }
+    }
+
+    pub fn unrecordable(reason: String) -> Self {
+        CoreFailure::Unrecordable { at: Location::default(), reason }
+    }
+
+    pub fn out_of_fuel(needed: u64, remaining: u64) -> Self {
+        CoreFailure::OutOfFuel { at: Location::default(), needed, remaining }
+    }
+
+    pub fn recorded(value: Val) -> Self {
+        CoreFailure::Recorded { at: Location::default(), value }
+    }
+
+    pub fn at(&self) -> &Location {
+        match self {
+            CoreFailure::Halt { at, .. }
+            | CoreFailure::WrongType { at, .. }
+            | CoreFailure::WrongLength { at, .. }
+            | CoreFailure::MissingKey { at, .. }
+            | CoreFailure::UnknownVariant { at, .. }
+            | CoreFailure::NotLocked { at, .. }
+            | CoreFailure::Overflow { at, .. }
+            | CoreFailure::ArityMismatch { at, .. }
+            | CoreFailure::Disallowed { at }
+            | CoreFailure::Cancelled { at }
+            | CoreFailure::NoExecutor { at }
+            | CoreFailure::Diverged { at, .. }
+            | CoreFailure::Unrecordable { at, .. }
+            | CoreFailure::OutOfFuel { at, .. }
+            | CoreFailure::Recorded { at, .. } => at,
+        }
+    }
+
+    pub fn at_mut(&mut self) -> &mut Location {
+        match self {
+            CoreFailure::Halt { at, .. }
+            | CoreFailure::WrongType { at, .. }
+            | CoreFailure::WrongLength { at, .. }
+            | CoreFailure::MissingKey { at, .. }
+            | CoreFailure::UnknownVariant { at, .. }
+            | CoreFailure::NotLocked { at, .. }
+            | CoreFailure::Overflow { at, .. }
+            | CoreFailure::ArityMismatch { at, .. }
+            | CoreFailure::Disallowed { at }
+            | CoreFailure::Cancelled { at }
+            | CoreFailure::NoExecutor { at }
+            | CoreFailure::Diverged { at, .. }
+            | CoreFailure::Unrecordable { at, .. }
+            | CoreFailure::OutOfFuel { at, .. }
+            | CoreFailure::Recorded { at, .. } => at,
+        }
+    }
+
+    /// Record the function in which the failure occurred, unless it is already known.
+    pub fn in_function(mut self, function: CoreFunction) -> Self {
+        let at = self.at_mut();
+        if at.function.is_none() {
+            at.function = Some(function);
+        }
+        self
+    }
+
+    /// Record the argument that caused the failure, unless it is already known.
+    pub fn at_arg(mut self, index: usize) -> Self {
+        let at = self.at_mut();
+        if at.arg.is_none() {
+            at.arg = Some(index);
+        }
+        self
+    }
+
+    /// The offending value, if the failure was caused by one.
+    pub fn value(&self) -> Option<&Val> {
+        match self {
+            CoreFailure::Halt { value, .. }
+            | CoreFailure::WrongType { value, .. }
+            | CoreFailure::WrongLength { value, .. }
+            | CoreFailure::MissingKey { value, .. }
+            | CoreFailure::UnknownVariant { value, .. }
+            | CoreFailure::NotLocked { value, .. }
+            | CoreFailure::Recorded { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The type the offending value should have had.
+    pub fn expected_type(&self) -> Option<Type> {
+        match self {
+            CoreFailure::WrongType { expected, .. } => Some(*expected),
+            _ => None,
+        }
+    }
+
+    /// The name of the variant in snake case, e.g. `"wrong_type"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CoreFailure::Halt { .. } => "halt",
+            CoreFailure::WrongType { .. } => "wrong_type",
+            CoreFailure::WrongLength { .. } => "wrong_length",
+            CoreFailure::MissingKey { .. } => "missing_key",
+            CoreFailure::UnknownVariant { .. } => "unknown_variant",
+            CoreFailure::NotLocked { .. } => "not_locked",
+            CoreFailure::Overflow { .. } => "overflow",
+            CoreFailure::ArityMismatch { .. } => "arity_mismatch",
+            CoreFailure::Disallowed { .. } => "disallowed",
+            CoreFailure::Cancelled { .. } => "cancelled",
+            CoreFailure::NoExecutor { .. } => "no_executor",
+            CoreFailure::Diverged { .. } => "diverged",
+            CoreFailure::Unrecordable { .. } => "unrecordable",
+            CoreFailure::OutOfFuel { .. } => "out_of_fuel",
+            CoreFailure::Recorded { .. } => "recorded",
+        }
+    }
+}
+
+impl<C: VvvmConfig> CoreFailure<V<C>> {
+    /// The failure as a value that scripts can inspect: a map with the entries `"kind"` (see
+    /// `kind`), `"function"` and `"argument"` (`nil` if unknown), `"message"`, and depending on
+    /// the kind `"expected"`, `"key"`, `"value"`, `"operands"`, `"actual"`, `"reason"`,
+    /// `"needed"` and `"remaining"`. A recorded failure is the map that was recorded.
+    pub fn to_value(&self) -> V<C> {
+        V::Map(self.entries().into_iter().collect())
+    }
+
+    pub(crate) fn entries(&self) -> Vec<(V<C>, V<C>)> {
+        if let CoreFailure::Recorded { value: V::Map(m), .. } = self {
+            return m.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+        }
+
+        let at = self.at();
+        let mut entries = vec![
+            (V::string("kind"), V::string(self.kind())),
+            (V::string("function"), at.function.as_ref().map_or(V::nil(), |f| V::string(f.name()))),
+            (V::string("argument"), at.arg.map_or(V::nil(), |arg| V::int(arg as i64))),
+            (V::string("message"), V::string(&self.to_string())),
+        ];
+
+        match self {
+            CoreFailure::Halt { value, .. }
+            | CoreFailure::UnknownVariant { value, .. }
+            | CoreFailure::NotLocked { value, .. }
+            | CoreFailure::Recorded { value, .. } => {
+                entries.push((V::string("value"), value.clone()));
+            }
+            CoreFailure::WrongType { expected, value, .. } => {
+                entries.push((V::string("expected"), V::string(&expected.to_string())));
+                entries.push((V::string("value"), value.clone()));
+            }
+            CoreFailure::WrongLength { expected, value, .. } => {
+                entries.push((V::string("expected"), V::int(*expected as i64)));
+                entries.push((V::string("value"), value.clone()));
+            }
+            CoreFailure::MissingKey { key, value, .. } => {
+                entries.push((V::string("key"), key.clone()));
+                entries.push((V::string("value"), value.clone()));
+            }
+            CoreFailure::Overflow { operands, .. } => {
+                entries.push((V::string("operands"), V::Array(operands.iter().map(|n| V::int(*n)).collect())));
+            }
+            CoreFailure::ArityMismatch { expected, actual, .. } => {
+                entries.push((V::string("expected"), V::int(*expected as i64)));
+                entries.push((V::string("actual"), V::int(*actual as i64)));
+            }
+            CoreFailure::Disallowed { .. }
+            | CoreFailure::Cancelled { .. }
+            | CoreFailure::NoExecutor { .. } => {}
+            CoreFailure::Diverged { reason, .. }
+            | CoreFailure::Unrecordable { reason, .. } => {
+                entries.push((V::string("reason"), V::string(reason)));
+            }
+            CoreFailure::OutOfFuel { needed, remaining, .. } => {
+                entries.push((V::string("needed"), V::int(*needed as i64)));
+                entries.push((V::string("remaining"), V::int(*remaining as i64)));
+            }
+        }
+
+        entries
+    }
+}
+
+impl<C: VvvmConfig> IntoValue<C> for CoreFailure<V<C>> {
+    fn into_value(self) -> V<C> {
+        self.to_value()
+    }
+}
+
+impl<C: VvvmConfig> VvvmFailure<C> {
+    /// The failure as a value, see `CoreFailure::to_value`. Failures of the configuration are
+    /// represented as a map with the kind `"other"` and a `"value"` given by
+    /// `VvvmConfig::failure_to_value`.
+    pub fn to_value(&self) -> V<C> {
+        V::Map(self.entries().into_iter().collect())
+    }
+
+    pub(crate) fn entries(&self) -> Vec<(V<C>, V<C>)> {
+        match self {
+            VvvmFailure::Core(e) => e.entries(),
+            VvvmFailure::Other(e) => vec![
+                (V::string("kind"), V::string("other")),
+                (V::string("value"), C::failure_to_value(e).unwrap_or_else(V::nil)),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(function) = &self.function {
+            write!(f, "{}: ", function.name())?;
+        }
+        if let Some(arg) = self.arg {
+            write!(f, "argument {}: ", arg)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Val: fmt::Debug> fmt::Display for CoreFailure<Val> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.at())?;
+        match self {
+            CoreFailure::Halt { value, .. } => write!(f, "halted with {:?}", value),
+            CoreFailure::WrongType { expected, value, .. } => {
+                write!(f, "expected {}, got {:?}", expected, value)
+            }
+            CoreFailure::WrongLength { expected, value, .. } => {
+                write!(f, "expected {} entries, got {:?}", expected, value)
+            }
+            CoreFailure::MissingKey { key, value, .. } => write!(f, "no entry for {:?} in {:?}", key, value),
+            CoreFailure::UnknownVariant { value, .. } => write!(f, "unknown variant {:?}", value),
+            CoreFailure::NotLocked { value, .. } => write!(f, "{:?} is not locked", value),
+            CoreFailure::Overflow { operands, .. } => write!(f, "integer overflow on {:?}", operands),
+            CoreFailure::ArityMismatch { expected, actual, .. } => {
+                write!(f, "expected {} arguments, got {}", expected, actual)
+            }
+            CoreFailure::Disallowed { .. } => write!(f, "not allowed in this sandbox"),
+            CoreFailure::Cancelled { .. } => write!(f, "cancelled"),
+            CoreFailure::NoExecutor { .. } => write!(f, "not running in an executor that can run closures"),
+            CoreFailure::Diverged { reason, .. } => write!(f, "diverged from the recording: {}", reason),
+            CoreFailure::Unrecordable { reason, .. } => write!(f, "cannot be recorded: {}", reason),
+            CoreFailure::OutOfFuel { needed, remaining, .. } => {
+                write!(f, "out of fuel, needed {} but {} remaining", needed, remaining)
+            }
+            CoreFailure::Recorded { value, .. } => write!(f, "recorded failure {:?}", value),
+        }
+    }
+}
+
+impl<Val: fmt::Debug> Error for CoreFailure<Val> {}
+
+impl<C: VvvmConfig> fmt::Debug for VvvmFailure<C> where
+    C::Failure: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VvvmFailure::Core(e) => f.debug_tuple("Core").field(e).finish(),
+            VvvmFailure::Other(e) => f.debug_tuple("Other").field(e).finish(),
+        }
+    }
+}
+
+impl<C: VvvmConfig> fmt::Display for VvvmFailure<C> where
+    C::Failure: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VvvmFailure::Core(e) => write!(f, "{}", e),
+            VvvmFailure::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Has no `source`: the `Display` of a `VvvmFailure` is that of the failure it wraps, reporting
+/// that one as the source would print it twice in error chains.
+impl<C: VvvmConfig> Error for VvvmFailure<C> where
+    C::Failure: fmt::Debug + fmt::Display,
+{}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use guvm_rs::{BuiltInSynchronousFunction, VirtualMachine};
+
+    use super::{CoreFailure, Location};
+    use crate::{V, DefaultConfig, DefaultV, VvvmFailure, SynchronousCoreFunction};
+    use crate::registry::CoreFunction;
+    use crate::signature::Type;
+
+    #[test]
+    fn context() {
+        let add = CoreFunction::from(SynchronousCoreFunction::IntAdd);
+        let sub = CoreFunction::from(SynchronousCoreFunction::IntSub);
+
+        let e = CoreFailure::wrong_type(Type::Int, DefaultV::nil()).at_arg(1).in_function(add.clone());
+        assert_eq!(e.at(), &Location { function: Some(add.clone()), arg: Some(1) });
+        // The innermost context wins.
+        let e = e.at_arg(0).in_function(sub);
+        assert_eq!(e.at(), &Location { function: Some(add.clone()), arg: Some(1) });
+
+        let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+        match SynchronousCoreFunction::IntAdd.invoke(&[V::int(1), V::boo(true)], &mut vm) {
+            Err(e) => {
+                assert_eq!(e.at(), &Location { function: Some(add), arg: Some(1) });
+                assert_eq!(e.expected_type(), Some(Type::Int));
+                assert_eq!(e.value(), Some(&V::boo(true)));
+            }
+            Ok(_) => panic!("expected a failure"),
+        }
+    }
+
+    #[test]
+    fn display() {
+        let e = CoreFailure::overflow(vec![i64::MAX, 1])
+            .in_function(CoreFunction::from(SynchronousCoreFunction::IntAdd));
+        assert_eq!(e.to_string(), format!("int_add: integer overflow on [{}, 1]", i64::MAX));
+
+        let e = CoreFailure::wrong_type(Type::Int, DefaultV::nil()).at_arg(0);
+        assert_eq!(e.to_string(), format!("argument 0: expected int, got {:?}", DefaultV::nil()));
+
+        let e = CoreFailure::<DefaultV>::arity_mismatch(2, 3);
+        assert_eq!(e.to_string(), "expected 2 arguments, got 3");
+    }
+
+    #[test]
+    fn errors() {
+        let e = CoreFailure::overflow(vec![1, 2]).at_arg(1);
+        let message = e.to_string();
+        assert!(Error::source(&e).is_none());
+
+        let e: VvvmFailure<DefaultConfig> = VvvmFailure::Core(e);
+        assert_eq!(e.to_string(), message);
+        assert!(e.source().is_none());
+
+        let boxed: Box<dyn Error> = Box::new(e);
+        assert_eq!(boxed.to_string(), message);
+    }
+}
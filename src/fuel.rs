@@ -0,0 +1,288 @@
+//! Bounding the work scripts can do by metering the synchronous core functions.
+//!
+//! Every `SynchronousCoreFunction` costs fuel (see `SynchronousCoreFunction::cost`), which is
+//! deducted from the `Fuel` that is entered on the current thread. If there is not enough fuel
+//! left, the function fails with `CoreFailure::OutOfFuel` without doing anything. Scripts that
+//! expect to run for long can call `fuel_yield` to suspend until the host has refueled.
+//!
+//! The budget is not tied to a virtual machine but to the thread: whatever runs while a `Fuel` is
+//! entered draws from it. To give each virtual machine a budget of its own, enter a separate
+//! `Fuel` whenever the machine runs, e.g. by wrapping the future driving it in `Fuel::meter`,
+//! which enters the fuel whenever the future is polled. Tasks that a script spawns draw from the
+//! fuel of the task that spawned them.
+
+use core::cell::{Cell, RefCell};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::rc::Rc;
+
+use crate::{V, VvvmConfig, CoreFailure, SynchronousCoreFunction};
+use crate::fun::util::add_waker;
+
+/// A budget of fuel, clones share the same budget.
+#[derive(Clone)]
+pub struct Fuel {
+    tank: Rc<Tank>,
+}
+
+struct Tank {
+    remaining: Cell<u64>,
+    consumed: Cell<u64>,
+    // Tasks waiting in `fuel_yield` for a refuel.
+    waiters: RefCell<Vec<Waker>>,
+}
+
+impl Fuel {
+    pub fn new(amount: u64) -> Self {
+        Fuel {
+            tank: Rc::new(Tank {
+                remaining: Cell::new(amount),
+                consumed: Cell::new(0),
+                waiters: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.tank.remaining.get()
+    }
+
+    /// The total amount of fuel used so far.
+    pub fn consumed(&self) -> u64 {
+        self.tank.consumed.get()
+    }
+
+    /// Add `amount` of fuel, and wake the tasks waiting for it.
+    pub fn refuel(&self, amount: u64) {
+        self.tank.remaining.set(self.tank.remaining.get().saturating_add(amount));
+        for waker in self.tank.waiters.borrow_mut().drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// Deduct `amount` if that much is left.
+    pub fn try_consume(&self, amount: u64) -> bool {
+        let remaining = self.tank.remaining.get();
+        if remaining >= amount {
+            self.tank.remaining.set(remaining - amount);
+            self.tank.consumed.set(self.tank.consumed.get().saturating_add(amount));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Meter the core functions invoked on the current thread with this fuel until the returned
+    /// guard is dropped, at which point the previously entered fuel (if any) is restored.
+    pub fn enter(&self) -> FuelGuard {
+        let previous = ACTIVE.with(|active| active.replace(Some(self.clone())));
+        FuelGuard { previous }
+    }
+
+    /// Wrap `f` so that this fuel is entered whenever it is polled.
+    pub fn meter<F: Future>(&self, f: F) -> Metered<F> {
+        Metered {
+            fuel: self.clone(),
+            future: Box::pin(f),
+        }
+    }
+}
+
+/// Restores the previously entered fuel when dropped.
+pub struct FuelGuard {
+    previous: Option<Fuel>,
+}
+
+impl Drop for FuelGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACTIVE.with(|active| active.replace(previous));
+    }
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<Fuel>> = RefCell::new(None);
+}
+
+/// A future with an entered `Fuel`, see `Fuel::meter`.
+pub struct Metered<F> {
+    fuel: Fuel,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Metered<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        let _guard = self.fuel.enter();
+        self.future.as_mut().poll(cx)
+    }
+}
+
+pub(crate) fn active() -> Option<Fuel> {
+    ACTIVE.with(|active| active.borrow().clone())
+}
+
+impl SynchronousCoreFunction {
+    /// The fuel it costs to invoke the function with `args`: one, plus the number of entries of
+    /// all array and map arguments, since the collection functions take time proportional to
+    /// those. `array_concat` additionally pays for the entries of the concatenated arrays, and
+    /// `fuel_remaining` is free so that scripts can always check whether to call `fuel_yield`.
+    pub fn cost<C: VvvmConfig>(&self, args: &[V<C>]) -> u64 {
+        if let SynchronousCoreFunction::FuelRemaining = self {
+            return 0;
+        }
+
+        let mut cost = 1;
+        for arg in args {
+            cost += size(arg);
+            if let (SynchronousCoreFunction::ArrayConcat, V::Array(arrays)) = (self, arg) {
+                cost += arrays.iter().map(size).sum::<u64>();
+            }
+        }
+        cost
+    }
+}
+
+fn size<C: VvvmConfig>(v: &V<C>) -> u64 {
+    match v {
+        V::Array(a) => a.len() as u64,
+        V::Map(m) => m.len() as u64,
+        _ => 0,
+    }
+}
+
+/// Deduct the cost of invoking `f` with `args` from the entered fuel, if any.
+pub(crate) fn consume<C: VvvmConfig>(f: &SynchronousCoreFunction, args: &[V<C>]) -> Result<(), CoreFailure<V<C>>> {
+    match active() {
+        None => Ok(()),
+        Some(fuel) => {
+            let cost = f.cost(args);
+            if fuel.try_consume(cost) {
+                Ok(())
+            } else {
+                Err(CoreFailure::out_of_fuel(cost, fuel.remaining()))
+            }
+        }
+    }
+}
+
+/// Resolves once the fuel has at least `amount` left, yielding to other tasks at least once.
+pub(crate) struct FuelYield {
+    pub(crate) fuel: Option<Fuel>,
+    pub(crate) amount: u64,
+    pub(crate) yielded: bool,
+}
+
+impl Future for FuelYield {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if !self.yielded {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        match &self.fuel {
+            Some(fuel) if fuel.remaining() < self.amount => {
+                add_waker(&mut fuel.tank.waiters.borrow_mut(), cx.waker());
+                Poll::Pending
+            }
+            _ => Poll::Ready(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use guvm_rs::{BuiltInSynchronousFunction, VirtualMachine};
+
+    use super::{Fuel, FuelYield, active};
+    use crate::{V, DefaultConfig, DefaultV, VvvmResult, CoreFailure, SynchronousCoreFunction};
+    use crate::executor::Executor;
+    use crate::future::CoreFuture;
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    // Counts from zero to `n` with `int_add`, waiting for a refuel whenever the fuel runs out.
+    // Resolves to the number of times it ran out.
+    fn count_to(n: i64) -> impl Future<Output = VvvmResult<DefaultConfig>> {
+        async move {
+            let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+            let (mut count, mut out_of_fuel) = (0, 0);
+            while count < n {
+                match SynchronousCoreFunction::IntAdd.invoke(&[V::int(count), V::int(1)], &mut vm) {
+                    Ok(V::Int(next)) => count = next,
+                    Err(CoreFailure::OutOfFuel { .. }) => {
+                        out_of_fuel += 1;
+                        FuelYield { fuel: active(), amount: 1, yielded: false }.await;
+                    }
+                    _ => panic!("expected a sum or running out of fuel"),
+                }
+                // Give other tasks a chance to run in between.
+                CoreFuture::<DefaultV>::yield_then(V::nil()).await?;
+            }
+            Ok(V::int(out_of_fuel))
+        }
+    }
+
+    #[test]
+    fn refuel_resumes_yielding_tasks() {
+        let mut executor: Executor<DefaultConfig> = Executor::new();
+        let fuel = Fuel::new(2);
+        let id = executor.spawn(fuel.meter(count_to(5)));
+
+        assert!(executor.run_until_stalled());
+        assert_eq!(executor.pending(), 1);
+        assert_eq!((fuel.remaining(), fuel.consumed()), (0, 2));
+
+        fuel.refuel(3);
+        assert!(!executor.run_until_stalled());
+        assert_eq!(executor.take_result(id).unwrap().ok(), Some(V::int(1)));
+        assert_eq!((fuel.remaining(), fuel.consumed()), (0, 5));
+    }
+
+    #[test]
+    fn separate_budgets() {
+        let mut executor: Executor<DefaultConfig> = Executor::new();
+        let (first, second) = (Fuel::new(10), Fuel::new(10));
+        let first_id = executor.spawn(first.meter(count_to(3)));
+        let second_id = executor.spawn(second.meter(count_to(6)));
+        let unmetered = executor.spawn(count_to(20));
+
+        let results = executor.run();
+        for id in &[first_id, second_id, unmetered] {
+            assert_eq!(results[id].as_ref().ok(), Some(&V::int(0)));
+        }
+        assert_eq!((first.consumed(), second.consumed()), (3, 6));
+    }
+
+    #[test]
+    fn waiting_registers_one_waker() {
+        let fuel = Fuel::new(0);
+        let waker = Waker::from(Arc::new(Noop));
+        let mut cx = Context::from_waker(&waker);
+
+        let mut wait = FuelYield { fuel: Some(fuel.clone()), amount: 1, yielded: false };
+        for _ in 0..3 {
+            assert_eq!(Pin::new(&mut wait).poll(&mut cx), Poll::Pending);
+        }
+        assert_eq!(fuel.tank.waiters.borrow().len(), 1);
+
+        fuel.refuel(1);
+        assert!(fuel.tank.waiters.borrow().is_empty());
+        assert_eq!(Pin::new(&mut wait).poll(&mut cx), Poll::Ready(()));
+    }
+}
@@ -2,45 +2,180 @@ use core::pin::Pin;
 use core::task::Context;
 use core::task::Poll;
 use core::cmp::Ordering;
-use core::future::{Future, Ready, ready};
+use core::future::Future;
 
 use gc::{Gc, GcCell, Trace, Finalize, custom_trace};
 use gc_derive::{Trace, Finalize};
 
 use guvm_rs::{Value, BuiltInAsyncFunction, BuiltInSynchronousFunction, Closure, Arity, VirtualMachine};
 
-use crate::{V, ValueBaseOrdered, ValueBase, VvvmFailure, VvvmFuture};
-mod util;
+use crate::{V, VvvmConfig, VvvmFailure, VvvmFuture};
+use crate::registry::CoreFunction;
+use crate::failure::CoreFailure;
+use crate::future::CoreFuture;
+use crate::sandbox;
+use crate::record;
+pub(crate) mod util;
 mod value;
 mod order;
 mod boolean;
 mod float;
 mod int;
+mod array;
+mod function;
+mod task;
+mod channel;
+mod time;
+mod sync;
+mod generator;
+mod fuel;
+
+// Defines an enum of core functions together with their metadata, so that the name, module, arity
+// and purity of each function are declared in exactly one place.
+macro_rules! core_functions {
+    (
+        $(#[$attr:meta])*
+        $enum_name:ident {
+            $($variant:ident => [$name:literal, $module:literal, $params:expr, $pure:literal],)*
+        }
+    ) => (
+        $(#[$attr])*
+        #[derive(Clone, Trace, Finalize, PartialEq, Eq, PartialOrd, Ord, Debug)]
+        pub enum $enum_name {
+            $($variant,)*
+        }
+
+        impl $enum_name {
+            /// All functions, in declaration order.
+            pub const ALL: &'static [$enum_name] = &[$($enum_name::$variant,)*];
+
+            /// The stable name of the function, by which programs and configuration files refer
+            /// to it.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($enum_name::$variant => $name,)*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some($enum_name::$variant),)*
+                    _ => None,
+                }
+            }
+
+            /// The name of the module of the standard library the function belongs to.
+            pub fn module(&self) -> &'static str {
+                match self {
+                    $($enum_name::$variant => $module,)*
+                }
+            }
+
+            /// The parameters of the function, a plain number stands for that many required ones.
+            pub fn parameters(&self) -> Parameters {
+                match self {
+                    $($enum_name::$variant => Parameters::from($params),)*
+                }
+            }
+
+            pub fn arity(&self) -> Arity {
+                self.parameters().arity()
+            }
+
+            /// Whether the function is free of effects, i.e. its result depends on nothing but its
+            /// arguments and it never halts or suspends.
+            pub fn is_pure(&self) -> bool {
+                match self {
+                    $($enum_name::$variant => $pure,)*
+                }
+            }
+        }
+    );
+}
+
+/// The logical parameters of a built-in function, which always receives exactly `arity()` arguments.
+///
+/// Arguments are packed as follows: the `required` parameters come first, followed by the
+/// `optional` ones, for which `nil` is passed to leave them out. If the function is variadic
+/// (`rest`), all remaining arguments are packed into an array that is passed as the last argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parameters {
+    pub required: usize,
+    pub optional: usize,
+    pub rest: bool,
+}
+
+impl Parameters {
+    pub fn fixed(required: usize) -> Self {
+        Parameters {
+            required,
+            optional: 0,
+            rest: false,
+        }
+    }
+
+    pub fn optional(required: usize, optional: usize) -> Self {
+        Parameters {
+            required,
+            optional,
+            rest: false,
+        }
+    }
+
+    pub fn variadic(required: usize) -> Self {
+        Parameters {
+            required,
+            optional: 0,
+            rest: true,
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
+        (self.required + self.optional + if self.rest { 1 } else { 0 }) as Arity
+    }
+
+    /// Whether a call with `n` logical arguments is valid.
+    pub fn accepts(&self, n: usize) -> bool {
+        n >= self.required && (self.rest || n <= self.required + self.optional)
+    }
+
+    /// Pack logical arguments according to the convention described above, `None` if their
+    /// number is not accepted.
+    pub fn pack<C: VvvmConfig>(&self, mut args: Vec<V<C>>) -> Option<Vec<V<C>>> {
+        if !self.accepts(args.len()) {
+            return None;
+        }
+
+        let positional = self.required + self.optional;
+        let rest: Vec<V<C>> = if args.len() > positional {
+            args.split_off(positional)
+        } else {
+            Vec::new()
+        };
+        while args.len() < positional {
+            args.push(V::nil());
+        }
+        if self.rest {
+            args.push(V::Array(rest.into_iter().collect()));
+        }
+        Some(args)
+    }
+}
+
+impl From<usize> for Parameters {
+    fn from(required: usize) -> Self {
+        Parameters::fixed(required)
+    }
+}
 
 #[derive(Finalize)]
-pub enum Fun<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
-    SynchronousFunction(SynchronousFun<SS, DS>),
-    AsynchronousFunction(AsynchronousFun<SA, DA>),
-    Closure(Closure<V<SS, SA, DS, DA, F, Fut>>),
-}
-
-unsafe impl<SS, SA, DS, DA, F, Fut> Trace for Fun<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+pub enum Fun<C: VvvmConfig> {
+    SynchronousFunction(SynchronousFun<C>),
+    AsynchronousFunction(AsynchronousFun<C>),
+    Closure(Closure<V<C>>),
+}
+
+unsafe impl<C: VvvmConfig> Trace for Fun<C> {
     custom_trace!(this, {
         match this {
             Fun::SynchronousFunction(f) => mark(f),
@@ -50,13 +185,7 @@ where
     });
 }
 
-impl<SS, SA, DS, DA, F, Fut> Clone for Fun<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered + Clone,
-    DS: ValueBase + Clone,
-    SA: ValueBaseOrdered + Clone,
-    DA: ValueBase + Clone,
-{
+impl<C: VvvmConfig> Clone for Fun<C> {
     fn clone(&self) -> Self {
         match self {
             Fun::SynchronousFunction(f) => Fun::SynchronousFunction(f.clone()),
@@ -66,15 +195,7 @@ where
     }
 }
 
-impl<SS, SA, DS, DA, F, Fut> PartialEq for Fun<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> PartialEq for Fun<C> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Fun::SynchronousFunction(a), Fun::SynchronousFunction(b)) => a == b,
@@ -85,39 +206,15 @@ where
     }
 }
 
-impl<SS, SA, DS, DA, F, Fut> Eq for Fun<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{}
+impl<C: VvvmConfig> Eq for Fun<C> {}
 
-impl<SS, SA, DS, DA, F, Fut> PartialOrd for Fun<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> PartialOrd for Fun<C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<SS, SA, DS, DA, F, Fut> Ord for Fun<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> Ord for Fun<C> {
     fn cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
             (Fun::SynchronousFunction(a), Fun::SynchronousFunction(b)) => a.cmp(b),
@@ -168,17 +265,40 @@ where
     }
 }
 
-#[derive(Clone, Trace, Finalize)]
-pub enum SynchronousFun<S, D> {
+#[derive(Finalize)]
+pub enum SynchronousFun<C: VvvmConfig> {
     Core(SynchronousCoreFunction),
-    StaticSynchronous(S),
+    StaticSynchronous(C::StaticSynchronous),
     Dynamic {
         ordinal: usize,
-        fun: DynamicSynchronous<D>,
+        fun: DynamicSynchronous<C>,
     },
 }
 
-impl<S: PartialEq, D> PartialEq for SynchronousFun<S, D> {
+unsafe impl<C: VvvmConfig> Trace for SynchronousFun<C> {
+    custom_trace!(this, {
+        match this {
+            SynchronousFun::Core(_) => {}
+            SynchronousFun::StaticSynchronous(ss) => mark(ss),
+            SynchronousFun::Dynamic {fun: ds, ..} => mark(ds),
+        }
+    });
+}
+
+impl<C: VvvmConfig> Clone for SynchronousFun<C> {
+    fn clone(&self) -> Self {
+        match self {
+            SynchronousFun::Core(f) => SynchronousFun::Core(f.clone()),
+            SynchronousFun::StaticSynchronous(ss) => SynchronousFun::StaticSynchronous(ss.clone()),
+            SynchronousFun::Dynamic {ordinal, fun} => SynchronousFun::Dynamic {
+                ordinal: *ordinal,
+                fun: fun.clone(),
+            },
+        }
+    }
+}
+
+impl<C: VvvmConfig> PartialEq for SynchronousFun<C> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (SynchronousFun::Core(a), SynchronousFun::Core(b)) => a == b,
@@ -189,15 +309,15 @@ impl<S: PartialEq, D> PartialEq for SynchronousFun<S, D> {
     }
 }
 
-impl<S: Eq, D> Eq for SynchronousFun<S, D> {}
+impl<C: VvvmConfig> Eq for SynchronousFun<C> {}
 
-impl<S: Ord, D> PartialOrd for SynchronousFun<S, D> {
+impl<C: VvvmConfig> PartialOrd for SynchronousFun<C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<S: Ord, D> Ord for SynchronousFun<S, D> {
+impl<C: VvvmConfig> Ord for SynchronousFun<C> {
     fn cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
             (SynchronousFun::Core(a), SynchronousFun::Core(b)) => a.cmp(b),
@@ -210,17 +330,10 @@ impl<S: Ord, D> Ord for SynchronousFun<S, D> {
     }
 }
 
-impl<SS, SA, DS, DA, F, Fut> BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>> for SynchronousFun<SS, DS> where
-    SS: ValueBaseOrdered + BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>>,
-    DS: ValueBase + BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>>,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> BuiltInSynchronousFunction<V<C>, VvvmFailure<C>> for SynchronousFun<C> {
     fn arity(&self) -> Arity {
         match self {
-            SynchronousFun::Core(f) => BuiltInSynchronousFunction::<V<SS, SA, DS, DA, F, Fut>, CoreFailure<V<SS, SA, DS, DA, F, Fut>>>::arity(f),
+            SynchronousFun::Core(f) => BuiltInSynchronousFunction::<V<C>, CoreFailure<V<C>>>::arity(f),
             SynchronousFun::StaticSynchronous(ss) => ss.arity(),
             SynchronousFun::Dynamic {fun: ds, ..} => ds.arity(),
         }
@@ -228,9 +341,10 @@ impl<SS, SA, DS, DA, F, Fut> BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut
 
     fn invoke(
         &mut self,
-        args: &[V<SS, SA, DS, DA, F, Fut>],
-        vm: &mut VirtualMachine<V<SS, SA, DS, DA, F, Fut>>,
-    ) -> Result<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>> {
+        args: &[V<C>],
+        vm: &mut VirtualMachine<V<C>>,
+    ) -> Result<V<C>, VvvmFailure<C>> {
+        check_arity(self.arity(), args)?;
         match self {
             SynchronousFun::Core(f) => f.invoke(args, vm).map_err(|e| VvvmFailure::Core(e)),
             SynchronousFun::StaticSynchronous(ss) => ss.invoke(args, vm),
@@ -239,32 +353,44 @@ impl<SS, SA, DS, DA, F, Fut> BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut
     }
 }
 
-#[derive(Clone, Trace, Finalize)]
-pub enum DynamicSynchronous<D> {
+#[derive(Finalize)]
+pub enum DynamicSynchronous<C: VvvmConfig> {
     Core(DynamicCoreFunction),
-    Custom(D),
+    Custom(C::DynamicSynchronous),
 }
 
-impl<SS, SA, DS, DA, F, Fut> BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>> for DynamicSynchronous<DS> where
-    SS: ValueBaseOrdered,
-    DS: ValueBase + BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>>,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+unsafe impl<C: VvvmConfig> Trace for DynamicSynchronous<C> {
+    custom_trace!(this, {
+        match this {
+            DynamicSynchronous::Core(_) => {}
+            DynamicSynchronous::Custom(ds) => mark(ds),
+        }
+    });
+}
+
+impl<C: VvvmConfig> Clone for DynamicSynchronous<C> {
+    fn clone(&self) -> Self {
+        match self {
+            DynamicSynchronous::Core(f) => DynamicSynchronous::Core(f.clone()),
+            DynamicSynchronous::Custom(ds) => DynamicSynchronous::Custom(ds.clone()),
+        }
+    }
+}
+
+impl<C: VvvmConfig> BuiltInSynchronousFunction<V<C>, VvvmFailure<C>> for DynamicSynchronous<C> {
     fn arity(&self) -> Arity {
         match self {
-            DynamicSynchronous::Core(f) => BuiltInSynchronousFunction::<V<SS, SA, DS, DA, F, Fut>, CoreFailure<V<SS, SA, DS, DA, F, Fut>>>::arity(f),
+            DynamicSynchronous::Core(f) => BuiltInSynchronousFunction::<V<C>, CoreFailure<V<C>>>::arity(f),
             DynamicSynchronous::Custom(ds) => ds.arity(),
         }
     }
 
     fn invoke(
         &mut self,
-        args: &[V<SS, SA, DS, DA, F, Fut>],
-        vm: &mut VirtualMachine<V<SS, SA, DS, DA, F, Fut>>,
-    ) -> Result<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>> {
+        args: &[V<C>],
+        vm: &mut VirtualMachine<V<C>>,
+    ) -> Result<V<C>, VvvmFailure<C>> {
+        check_arity(self.arity(), args)?;
         match self {
             DynamicSynchronous::Core(f) => f.invoke(args, vm).map_err(|e| VvvmFailure::Core(e)),
             DynamicSynchronous::Custom(ds) => ds.invoke(args, vm),
@@ -272,17 +398,40 @@ impl<SS, SA, DS, DA, F, Fut> BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut
     }
 }
 
-#[derive(Clone, Trace, Finalize)]
-pub enum AsynchronousFun<S, D> {
+#[derive(Finalize)]
+pub enum AsynchronousFun<C: VvvmConfig> {
     Core(AsynchronousCoreFunction),
-    StaticAsynchronous(S),
+    StaticAsynchronous(C::StaticAsynchronous),
     Dynamic {
         ordinal: usize,
-        fun: D,
+        fun: C::DynamicAsynchronous,
     },
 }
 
-impl<S: PartialEq, D> PartialEq for AsynchronousFun<S, D> {
+unsafe impl<C: VvvmConfig> Trace for AsynchronousFun<C> {
+    custom_trace!(this, {
+        match this {
+            AsynchronousFun::Core(_) => {}
+            AsynchronousFun::StaticAsynchronous(sa) => mark(sa),
+            AsynchronousFun::Dynamic {fun: da, ..} => mark(da),
+        }
+    });
+}
+
+impl<C: VvvmConfig> Clone for AsynchronousFun<C> {
+    fn clone(&self) -> Self {
+        match self {
+            AsynchronousFun::Core(f) => AsynchronousFun::Core(f.clone()),
+            AsynchronousFun::StaticAsynchronous(sa) => AsynchronousFun::StaticAsynchronous(sa.clone()),
+            AsynchronousFun::Dynamic {ordinal, fun} => AsynchronousFun::Dynamic {
+                ordinal: *ordinal,
+                fun: fun.clone(),
+            },
+        }
+    }
+}
+
+impl<C: VvvmConfig> PartialEq for AsynchronousFun<C> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (AsynchronousFun::Core(a), AsynchronousFun::Core(b)) => a == b,
@@ -293,15 +442,15 @@ impl<S: PartialEq, D> PartialEq for AsynchronousFun<S, D> {
     }
 }
 
-impl<S: Eq, D> Eq for AsynchronousFun<S, D> {}
+impl<C: VvvmConfig> Eq for AsynchronousFun<C> {}
 
-impl<S: Ord, D> PartialOrd for AsynchronousFun<S, D> {
+impl<C: VvvmConfig> PartialOrd for AsynchronousFun<C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<S: Ord, D> Ord for AsynchronousFun<S, D> {
+impl<C: VvvmConfig> Ord for AsynchronousFun<C> {
     fn cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
             (AsynchronousFun::Core(a), AsynchronousFun::Core(b)) => a.cmp(b),
@@ -314,17 +463,10 @@ impl<S: Ord, D> Ord for AsynchronousFun<S, D> {
     }
 }
 
-impl<SS, SA, DS, DA, F, Fut> BuiltInAsyncFunction<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>, VvvmFuture<V<SS, SA, DS, DA, F, Fut>, Fut>> for AsynchronousFun<SA, DA> where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered + BuiltInAsyncFunction<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>, VvvmFuture<V<SS, SA, DS, DA, F, Fut>, Fut>>,
-    DA: ValueBase + BuiltInAsyncFunction<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>, VvvmFuture<V<SS, SA, DS, DA, F, Fut>, Fut>>,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> BuiltInAsyncFunction<V<C>, VvvmFailure<C>, VvvmFuture<C>> for AsynchronousFun<C> {
     fn arity(&self) -> Arity {
         match self {
-            AsynchronousFun::Core(f) => BuiltInAsyncFunction::<V<SS, SA, DS, DA, F, Fut>, _, _>::arity(f),
+            AsynchronousFun::Core(f) => BuiltInAsyncFunction::<V<C>, _, CoreFuture<V<C>>>::arity(f),
             AsynchronousFun::StaticAsynchronous(sa) => sa.arity(),
             AsynchronousFun::Dynamic {fun: da, ..} => da.arity(),
         }
@@ -332,336 +474,291 @@ impl<SS, SA, DS, DA, F, Fut> BuiltInAsyncFunction<V<SS, SA, DS, DA, F, Fut>, Vvv
 
     fn invoke(
         &mut self,
-        args: &[V<SS, SA, DS, DA, F, Fut>],
-        vm: &mut VirtualMachine<V<SS, SA, DS, DA, F, Fut>>,
-    ) -> VvvmFuture<V<SS, SA, DS, DA, F, Fut>, Fut> {
+        args: &[V<C>],
+        vm: &mut VirtualMachine<V<C>>,
+    ) -> VvvmFuture<C> {
+        if let Err(e) = check_arity(self.arity(), args) {
+            return VvvmFuture::Core(CoreFuture::ready(Err(e)));
+        }
+        record::invoke(self, args, vm)
+    }
+}
+
+impl<C: VvvmConfig> AsynchronousFun<C> {
+    /// Invoke the function, bypassing the entered `Recorder`.
+    pub(crate) fn invoke_unrecorded(
+        &mut self,
+        args: &[V<C>],
+        vm: &mut VirtualMachine<V<C>>,
+    ) -> VvvmFuture<C> {
         match self {
-            AsynchronousFun::Core(f) => VvvmFuture::Core(BuiltInAsyncFunction::<V<SS, SA, DS, DA, F, Fut>, _, CoreFuture<V<SS, SA, DS, DA, F, Fut>>>::invoke(f, args, vm)),
+            AsynchronousFun::Core(f) => VvvmFuture::Core(BuiltInAsyncFunction::<V<C>, _, CoreFuture<V<C>>>::invoke(f, args, vm)),
             AsynchronousFun::StaticAsynchronous(sa) => sa.invoke(args, vm),
             AsynchronousFun::Dynamic {fun: da, ..} => da.invoke(args, vm),
         }
     }
 }
 
-#[derive(Clone, Trace, Finalize, PartialEq, Eq, PartialOrd, Ord)]
-pub enum AsynchronousCoreFunction {
-    PreemptiveYield,
-}
-
-impl<SS, SA, DS, DA, F, Fut> BuiltInAsyncFunction<V<SS, SA, DS, DA, F, Fut>, VvvmFailure<V<SS, SA, DS, DA, F, Fut>, F>, CoreFuture<V<SS, SA, DS, DA, F, Fut>>> for AsynchronousCoreFunction where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+core_functions! {
+    /// The asynchronous functions of the core library.
+    AsynchronousCoreFunction {
+        PreemptiveYield => ["preemptive_yield", "task", 0, false],
+        TaskSpawn => ["task_spawn", "task", 1, false],
+        TaskJoin => ["task_join", "task", 1, false],
+        TaskCancel => ["task_cancel", "task", 1, false],
+        TaskJoinAll => ["task_join_all", "task", 1, false],
+        TaskSelect => ["task_select", "task", 1, false],
+        ChannelSend => ["channel_send", "channel", 2, false],
+        ChannelRecv => ["channel_recv", "channel", 1, false],
+        TimeSleep => ["time_sleep", "time", 1, false],
+        TimeTimeout => ["time_timeout", "time", 2, false],
+        SyncMutexLock => ["sync_mutex_lock", "sync", 1, false],
+        SyncSemaphoreAcquire => ["sync_semaphore_acquire", "sync", 1, false],
+        SyncBarrierWait => ["sync_barrier_wait", "sync", 1, false],
+        SyncEventWait => ["sync_event_wait", "sync", 1, false],
+        GeneratorYield => ["generator_yield", "generator", 2, false],
+        GeneratorNext => ["generator_next", "generator", 1, false],
+        GeneratorCollect => ["generator_collect", "generator", 1, false],
+        GeneratorTake => ["generator_take", "generator", 2, false],
+        FuelYield => ["fuel_yield", "fuel", 1, false],
+    }
+}
+
+impl<C: VvvmConfig> BuiltInAsyncFunction<V<C>, VvvmFailure<C>, CoreFuture<V<C>>> for AsynchronousCoreFunction {
     fn arity(&self) -> Arity {
-        match self {
-            AsynchronousCoreFunction::PreemptiveYield => 0,
-        }
+        AsynchronousCoreFunction::arity(self)
     }
 
     fn invoke(
         &mut self,
-        _: &[V<SS, SA, DS, DA, F, Fut>],
-        _: &mut VirtualMachine<V<SS, SA, DS, DA, F, Fut>>,
-    ) -> CoreFuture<V<SS, SA, DS, DA, F, Fut>> {
-        match self {
-            AsynchronousCoreFunction::PreemptiveYield => ready(V::default()),
+        args: &[V<C>],
+        _: &mut VirtualMachine<V<C>>,
+    ) -> CoreFuture<V<C>> {
+        if let Err(e) = check_arity(AsynchronousCoreFunction::arity(self), args) {
+            return CoreFuture::ready(Err(e.in_function(self.clone().into())));
+        }
+        if let Err(e) = sandbox::check(self.clone().into()) {
+            return CoreFuture::ready(Err(e));
         }
+
+        let result = match self {
+            AsynchronousCoreFunction::PreemptiveYield => Ok(CoreFuture::yield_then(V::nil())),
+            AsynchronousCoreFunction::TaskSpawn => task::spawn(&args[0]),
+            AsynchronousCoreFunction::TaskJoin => task::join(&args[0]),
+            AsynchronousCoreFunction::TaskCancel => task::cancel(&args[0]),
+            AsynchronousCoreFunction::TaskJoinAll => task::join_all(&args[0]),
+            AsynchronousCoreFunction::TaskSelect => task::select(&args[0]),
+            AsynchronousCoreFunction::ChannelSend => channel::send(&args[0], &args[1]),
+            AsynchronousCoreFunction::ChannelRecv => channel::recv(&args[0]),
+            AsynchronousCoreFunction::TimeSleep => time::sleep(&args[0]),
+            AsynchronousCoreFunction::TimeTimeout => time::timeout(&args[0], &args[1]),
+            AsynchronousCoreFunction::SyncMutexLock => sync::mutex_lock(&args[0]),
+            AsynchronousCoreFunction::SyncSemaphoreAcquire => sync::semaphore_acquire(&args[0]),
+            AsynchronousCoreFunction::SyncBarrierWait => sync::barrier_wait(&args[0]),
+            AsynchronousCoreFunction::SyncEventWait => sync::event_wait(&args[0]),
+            AsynchronousCoreFunction::GeneratorYield => generator::yield_(&args[0], &args[1]),
+            AsynchronousCoreFunction::GeneratorNext => generator::next(&args[0]),
+            AsynchronousCoreFunction::GeneratorCollect => generator::collect(&args[0]),
+            AsynchronousCoreFunction::GeneratorTake => generator::take(&args[0], &args[1]),
+            AsynchronousCoreFunction::FuelYield => fuel::yield_(&args[0]),
+        };
+
+        result.unwrap_or_else(|e| CoreFuture::ready(Err(e.in_function(self.clone().into()))))
     }
 }
 
-pub type CoreFuture<Val> = Ready<Val>;
-
-#[derive(Clone, Trace, Finalize, PartialEq, Eq, PartialOrd, Ord)]
-pub enum SynchronousCoreFunction {
-    ValueHalt,
-    ValueTypeOf,
-    ValueTruthy,
-    ValueFalsey,
-
-    OrderTotalCompare,
-    OrderTotalLt,
-    OrderTotalLeq,
-    OrderTotalEq,
-    OrderTotalGeq,
-    OrderTotalGt,
-    OrderTotalNeq,
-    OrderTotalMin,
-    OrderTotalMax,
-
-    OrderPartialCompare,
-    OrderPartialLt,
-    OrderPartialLeq,
-    OrderPartialEq,
-    OrderPartialGeq,
-    OrderPartialGt,
-    OrderPartialNeq,
-    OrderPartialGreatestLowerBound,
-    OrderPartialLeastUpperBound,
-
-    BoolNot,
-    BoolAnd,
-    BoolOr,
-    BoolIf,
-    BoolIff,
-    BoolXor,
-
-    FloatAdd,
-    FloatSub,
-    FloatMul,
-    FloatDiv,
-    FloatMulAdd,
-    FloatNeg,
-    FloatFloor,
-    FloatCeil,
-    FloatRound,
-    FloatTrunc,
-    FloatFract,
-    FloatAbs,
-    FloatSignum,
-    FloatPow,
-    FloatSqrt,
-    FloatExp,
-    FloatExp2,
-    FloatLn,
-    FloatLog2,
-    FloatLog10,
-    FloatHypot,
-    FloatSin,
-    FloatCos,
-    FloatTan,
-    FloatAsin,
-    FloatAcos,
-    FloatAtan,
-    FloatAtan2,
-    FloatExpM1,
-    FloatLn1P,
-    FloatSinh,
-    FloatCosh,
-    FloatTanh,
-    FloatAsinh,
-    FloatAcosh,
-    FloatAtanh,
-    FloatIsNormal,
-    FloatToDegrees,
-    FloatToRadians,
-    FloatToInt,
-    FloatFromInt,
-    FloatToBits,
-    FloatFromBits,
-
-    IntSignum,
-    IntAdd,
-    IntSub,
-    IntMul,
-    IntDiv,
-    IntDivTrunc,
-    IntMod,
-    IntModTrunc,
-    IntNeg,
-    IntAbs,
-    IntPow,
-
-    IntCheckAdd,
-    IntCheckSub,
-    IntCheckMul,
-    IntCheckDiv,
-    IntCheckDivTrunc,
-    IntCheckMod,
-    IntCheckModTrunc,
-    IntCheckNeg,
-    IntCheckAbs,
-    IntCheckPow,
-
-    IntSatAdd,
-    IntSatSub,
-    IntSatMul,
-    IntSatPow,
-
-    IntWrapAdd,
-    IntWrapSub,
-    IntWrapMul,
-    IntWrapDiv,
-    IntWrapDivTrunc,
-    IntWrapMod,
-    IntWrapModTrunc,
-    IntWrapNeg,
-    IntWrapAbs,
-    IntWrapPow,
-
-    IntBitCountOnes,
-    IntBitCountZeros,
-    IntBitLeadingOnes,
-    IntBitLeadingZeros,
-    IntBitTrailingOnes,
-    IntBitTrailingZeros,
-    IntBitRotateLeft,
-    IntBitRotateRight,
-    IntBitReverseBytes,
-    IntBitReverseBits,
-    IntBitShl,
-    IntBitShr,
-
-
+/// Fail with `CoreFailure::ArityMismatch` unless there are exactly `arity` arguments.
+///
+/// Every `invoke` implementation of this crate checks this before touching the arguments, so host
+/// code may call them with arbitrary argument slices.
+pub fn check_arity<Val>(arity: Arity, args: &[Val]) -> Result<(), CoreFailure<Val>> {
+    if args.len() == arity as usize {
+        Ok(())
+    } else {
+        Err(CoreFailure::arity_mismatch(arity, args.len()))
+    }
+}
 
+core_functions! {
+    /// The synchronous functions of the core library.
+    SynchronousCoreFunction {
+        ValueHalt => ["value_halt", "value", 1, false],
+        ValueTypeOf => ["value_type_of", "value", 1, true],
+        ValueTruthy => ["value_truthy", "value", 1, true],
+        ValueFalsey => ["value_falsey", "value", 1, true],
+        ValuePretty => ["value_pretty", "value", 1, true],
+
+        OrderTotalCompare => ["order_total_compare", "order", 2, true],
+        OrderTotalLt => ["order_total_lt", "order", 2, true],
+        OrderTotalLeq => ["order_total_leq", "order", 2, true],
+        OrderTotalEq => ["order_total_eq", "order", 2, true],
+        OrderTotalGeq => ["order_total_geq", "order", 2, true],
+        OrderTotalGt => ["order_total_gt", "order", 2, true],
+        OrderTotalNeq => ["order_total_neq", "order", 2, true],
+        OrderTotalMin => ["order_total_min", "order", Parameters::variadic(1), true],
+        OrderTotalMax => ["order_total_max", "order", Parameters::variadic(1), true],
+
+        OrderPartialCompare => ["order_partial_compare", "order", 2, true],
+        OrderPartialLt => ["order_partial_lt", "order", 2, true],
+        OrderPartialLeq => ["order_partial_leq", "order", 2, true],
+        OrderPartialEq => ["order_partial_eq", "order", 2, true],
+        OrderPartialGeq => ["order_partial_geq", "order", 2, true],
+        OrderPartialGt => ["order_partial_gt", "order", 2, true],
+        OrderPartialNeq => ["order_partial_neq", "order", 2, true],
+        OrderPartialGreatestLowerBound => ["order_partial_greatest_lower_bound", "order", 2, true],
+        OrderPartialLeastUpperBound => ["order_partial_least_upper_bound", "order", 2, true],
+
+        BoolNot => ["bool_not", "bool", 1, true],
+        BoolAnd => ["bool_and", "bool", 2, true],
+        BoolOr => ["bool_or", "bool", 2, true],
+        BoolIf => ["bool_if", "bool", 2, true],
+        BoolIff => ["bool_iff", "bool", 2, true],
+        BoolXor => ["bool_xor", "bool", 2, true],
+
+        FloatAdd => ["float_add", "float", 2, true],
+        FloatSub => ["float_sub", "float", 2, true],
+        FloatMul => ["float_mul", "float", 2, true],
+        FloatDiv => ["float_div", "float", 2, true],
+        FloatMulAdd => ["float_mul_add", "float", 3, true],
+        FloatNeg => ["float_neg", "float", 1, true],
+        FloatFloor => ["float_floor", "float", 1, true],
+        FloatCeil => ["float_ceil", "float", 1, true],
+        FloatRound => ["float_round", "float", Parameters::optional(1, 1), true],
+        FloatTrunc => ["float_trunc", "float", 1, true],
+        FloatFract => ["float_fract", "float", 1, true],
+        FloatAbs => ["float_abs", "float", 1, true],
+        FloatSignum => ["float_signum", "float", 1, true],
+        FloatPow => ["float_pow", "float", 2, true],
+        FloatSqrt => ["float_sqrt", "float", 1, true],
+        FloatExp => ["float_exp", "float", 1, true],
+        FloatExp2 => ["float_exp2", "float", 1, true],
+        FloatLn => ["float_ln", "float", 1, true],
+        FloatLog2 => ["float_log2", "float", 1, true],
+        FloatLog10 => ["float_log10", "float", 1, true],
+        FloatHypot => ["float_hypot", "float", 2, true],
+        FloatSin => ["float_sin", "float", 1, true],
+        FloatCos => ["float_cos", "float", 1, true],
+        FloatTan => ["float_tan", "float", 1, true],
+        FloatAsin => ["float_asin", "float", 1, true],
+        FloatAcos => ["float_acos", "float", 1, true],
+        FloatAtan => ["float_atan", "float", 1, true],
+        FloatAtan2 => ["float_atan2", "float", 2, true],
+        FloatExpM1 => ["float_exp_m1", "float", 1, true],
+        FloatLn1P => ["float_ln_1p", "float", 1, true],
+        FloatSinh => ["float_sinh", "float", 1, true],
+        FloatCosh => ["float_cosh", "float", 1, true],
+        FloatTanh => ["float_tanh", "float", 1, true],
+        FloatAsinh => ["float_asinh", "float", 1, true],
+        FloatAcosh => ["float_acosh", "float", 1, true],
+        FloatAtanh => ["float_atanh", "float", 1, true],
+        FloatIsNormal => ["float_is_normal", "float", 1, true],
+        FloatToDegrees => ["float_to_degrees", "float", 1, true],
+        FloatToRadians => ["float_to_radians", "float", 1, true],
+        FloatToInt => ["float_to_int", "float", 1, true],
+        FloatFromInt => ["float_from_int", "float", 1, true],
+        FloatToBits => ["float_to_bits", "float", 1, true],
+        FloatFromBits => ["float_from_bits", "float", 1, true],
+
+        IntSignum => ["int_signum", "int", 1, true],
+        IntAdd => ["int_add", "int", 2, true],
+        IntSub => ["int_sub", "int", 2, true],
+        IntMul => ["int_mul", "int", 2, true],
+        IntDiv => ["int_div", "int", 2, true],
+        IntDivTrunc => ["int_div_trunc", "int", 2, true],
+        IntMod => ["int_mod", "int", 2, true],
+        IntModTrunc => ["int_mod_trunc", "int", 2, true],
+        IntNeg => ["int_neg", "int", 1, true],
+        IntAbs => ["int_abs", "int", 1, true],
+        IntPow => ["int_pow", "int", 2, true],
+
+        IntCheckAdd => ["int_check_add", "int", 2, true],
+        IntCheckSub => ["int_check_sub", "int", 2, true],
+        IntCheckMul => ["int_check_mul", "int", 2, true],
+        IntCheckDiv => ["int_check_div", "int", 2, true],
+        IntCheckDivTrunc => ["int_check_div_trunc", "int", 2, true],
+        IntCheckMod => ["int_check_mod", "int", 2, true],
+        IntCheckModTrunc => ["int_check_mod_trunc", "int", 2, true],
+        IntCheckNeg => ["int_check_neg", "int", 1, true],
+        IntCheckAbs => ["int_check_abs", "int", 1, true],
+        IntCheckPow => ["int_check_pow", "int", 2, true],
+
+        IntSatAdd => ["int_sat_add", "int", 2, true],
+        IntSatSub => ["int_sat_sub", "int", 2, true],
+        IntSatMul => ["int_sat_mul", "int", 2, true],
+        IntSatPow => ["int_sat_pow", "int", 2, true],
+
+        IntWrapAdd => ["int_wrap_add", "int", 2, true],
+        IntWrapSub => ["int_wrap_sub", "int", 2, true],
+        IntWrapMul => ["int_wrap_mul", "int", 2, true],
+        IntWrapDiv => ["int_wrap_div", "int", 2, true],
+        IntWrapDivTrunc => ["int_wrap_div_trunc", "int", 2, true],
+        IntWrapMod => ["int_wrap_mod", "int", 2, true],
+        IntWrapModTrunc => ["int_wrap_mod_trunc", "int", 2, true],
+        IntWrapNeg => ["int_wrap_neg", "int", 1, true],
+        IntWrapAbs => ["int_wrap_abs", "int", 1, true],
+        IntWrapPow => ["int_wrap_pow", "int", 2, true],
+
+        IntBitCountOnes => ["int_bit_count_ones", "int", 1, true],
+        IntBitCountZeros => ["int_bit_count_zeros", "int", 1, true],
+        IntBitLeadingOnes => ["int_bit_leading_ones", "int", 1, true],
+        IntBitLeadingZeros => ["int_bit_leading_zeros", "int", 1, true],
+        IntBitTrailingOnes => ["int_bit_trailing_ones", "int", 1, true],
+        IntBitTrailingZeros => ["int_bit_trailing_zeros", "int", 1, true],
+        IntBitRotateLeft => ["int_bit_rotate_left", "int", 2, true],
+        IntBitRotateRight => ["int_bit_rotate_right", "int", 2, true],
+        IntBitReverseBytes => ["int_bit_reverse_bytes", "int", 1, true],
+        IntBitReverseBits => ["int_bit_reverse_bits", "int", 1, true],
+        IntBitShl => ["int_bit_shl", "int", 2, true],
+        IntBitShr => ["int_bit_shr", "int", 2, true],
+
+        ArrayConcat => ["array_concat", "array", Parameters::variadic(0), true],
+
+        FunTry => ["fun_try", "fun", 2, false],
+
+        ChannelNew => ["channel_new", "channel", Parameters::optional(0, 1), false],
+        ChannelTryRecv => ["channel_try_recv", "channel", 1, false],
+        ChannelClose => ["channel_close", "channel", 1, false],
+
+        TimeNow => ["time_now", "time", 0, false],
+
+        SyncMutexNew => ["sync_mutex_new", "sync", 0, false],
+        SyncMutexUnlock => ["sync_mutex_unlock", "sync", 1, false],
+        SyncSemaphoreNew => ["sync_semaphore_new", "sync", 1, false],
+        SyncSemaphoreRelease => ["sync_semaphore_release", "sync", 1, false],
+        SyncBarrierNew => ["sync_barrier_new", "sync", 1, false],
+        SyncEventNew => ["sync_event_new", "sync", 0, false],
+        SyncEventSet => ["sync_event_set", "sync", 1, false],
+
+        GeneratorNew => ["generator_new", "generator", 1, false],
+        GeneratorClose => ["generator_close", "generator", 1, false],
+
+        FuelRemaining => ["fuel_remaining", "fuel", 0, false],
+    }
 }
+
 use SynchronousCoreFunction::*;
 
-impl<SS, SA, DS, DA, F, Fut> BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut>, CoreFailure<V<SS, SA, DS, DA, F, Fut>>> for SynchronousCoreFunction where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> BuiltInSynchronousFunction<V<C>, CoreFailure<V<C>>> for SynchronousCoreFunction {
     fn arity(&self) -> Arity {
-        match self {
-            ValueHalt => 1,
-            ValueTypeOf => 1,
-            ValueTruthy => 1,
-            ValueFalsey => 1,
-
-            OrderTotalCompare => 2,
-            OrderTotalLt => 2,
-            OrderTotalLeq => 2,
-            OrderTotalEq => 2,
-            OrderTotalGeq => 2,
-            OrderTotalGt => 2,
-            OrderTotalNeq => 2,
-            OrderTotalMin => 2,
-            OrderTotalMax => 2,
-
-            OrderPartialCompare => 2,
-            OrderPartialLt => 2,
-            OrderPartialLeq => 2,
-            OrderPartialEq => 2,
-            OrderPartialGeq => 2,
-            OrderPartialGt => 2,
-            OrderPartialNeq => 2,
-            OrderPartialGreatestLowerBound => 2,
-            OrderPartialLeastUpperBound => 2,
-
-            BoolNot => 1,
-            BoolAnd => 2,
-            BoolOr => 2,
-            BoolIf => 2,
-            BoolIff => 2,
-            BoolXor => 2,
-
-            FloatAdd => 2,
-            FloatSub => 2,
-            FloatMul => 2,
-            FloatDiv => 2,
-            FloatMulAdd => 3,
-            FloatNeg => 1,
-            FloatFloor => 1,
-            FloatCeil => 1,
-            FloatRound => 1,
-            FloatTrunc => 1,
-            FloatFract => 1,
-            FloatAbs => 1,
-            FloatSignum => 1,
-            FloatPow => 2,
-            FloatSqrt => 1,
-            FloatExp => 1,
-            FloatExp2 => 1,
-            FloatLn => 1,
-            FloatLog2 => 1,
-            FloatLog10 => 1,
-            FloatHypot => 2,
-            FloatSin => 1,
-            FloatCos => 1,
-            FloatTan => 1,
-            FloatAsin => 1,
-            FloatAcos => 1,
-            FloatAtan => 1,
-            FloatAtan2 => 2,
-            FloatExpM1 => 1,
-            FloatLn1P => 1,
-            FloatSinh => 1,
-            FloatCosh => 1,
-            FloatTanh => 1,
-            FloatAsinh => 1,
-            FloatAcosh => 1,
-            FloatAtanh => 1,
-            FloatIsNormal => 1,
-            FloatToDegrees => 1,
-            FloatToRadians => 1,
-            FloatToInt => 1,
-            FloatFromInt => 1,
-            FloatToBits => 1,
-            FloatFromBits => 1,
-
-            IntSignum => 1,
-            IntAdd => 2,
-            IntSub => 2,
-            IntMul => 2,
-            IntDiv => 2,
-            IntDivTrunc => 2,
-            IntMod => 2,
-            IntModTrunc => 2,
-            IntNeg => 1,
-            IntAbs => 1,
-            IntPow => 2,
-
-            IntCheckAdd => 2,
-            IntCheckSub => 2,
-            IntCheckMul => 2,
-            IntCheckDiv => 2,
-            IntCheckDivTrunc => 2,
-            IntCheckMod => 2,
-            IntCheckModTrunc => 2,
-            IntCheckNeg => 1,
-            IntCheckAbs => 1,
-            IntCheckPow => 2,
-
-            IntSatAdd => 2,
-            IntSatSub => 2,
-            IntSatMul => 2,
-            IntSatPow => 2,
-
-            IntWrapAdd => 2,
-            IntWrapSub => 2,
-            IntWrapMul => 2,
-            IntWrapDiv => 2,
-            IntWrapDivTrunc => 2,
-            IntWrapMod => 2,
-            IntWrapModTrunc => 2,
-            IntWrapNeg => 1,
-            IntWrapAbs => 1,
-            IntWrapPow => 2,
-
-            IntBitCountOnes => 1,
-            IntBitCountZeros => 1,
-            IntBitLeadingOnes => 1,
-            IntBitLeadingZeros => 1,
-            IntBitTrailingOnes => 1,
-            IntBitTrailingZeros => 1,
-            IntBitRotateLeft => 2,
-            IntBitRotateRight => 2,
-            IntBitReverseBytes => 1,
-            IntBitReverseBits => 1,
-            IntBitShl => 2,
-            IntBitShr => 2,
-
-            _ => unimplemented!(),
-        }
+        SynchronousCoreFunction::arity(self)
     }
 
     fn invoke(
         &mut self,
-        args: &[V<SS, SA, DS, DA, F, Fut>],
-        vm: &mut VirtualMachine<V<SS, SA, DS, DA, F, Fut>>,
-    ) -> Result<V<SS, SA, DS, DA, F, Fut>, CoreFailure<V<SS, SA, DS, DA, F, Fut>>> {
-        match self {
+        args: &[V<C>],
+        vm: &mut VirtualMachine<V<C>>,
+    ) -> Result<V<C>, CoreFailure<V<C>>> {
+        check_arity(SynchronousCoreFunction::arity(self), args)
+            .map_err(|e| e.in_function(self.clone().into()))?;
+        sandbox::check(self.clone().into())?;
+        crate::fuel::consume(self, args).map_err(|e| e.in_function(self.clone().into()))?;
+
+        let result = match self {
             ValueHalt => value::halt(&args[0]),
             ValueTypeOf => value::type_of(&args[0]),
             ValueTruthy => value::truthy(&args[0]),
             ValueFalsey => value::falsey(&args[0]),
+            ValuePretty => value::pretty(&args[0]),
 
             OrderTotalCompare => order::total_compare(&args[0], &args[1]),
             OrderTotalLt => order::total_lt(&args[0], &args[1]),
@@ -698,7 +795,7 @@ impl<SS, SA, DS, DA, F, Fut> BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut
             FloatNeg => float::neg(&args[0]),
             FloatFloor => float::floor(&args[0]),
             FloatCeil => float::ceil(&args[0]),
-            FloatRound => float::round(&args[0]),
+            FloatRound => float::round(&args[0], &args[1]),
             FloatTrunc => float::trunc(&args[0]),
             FloatFract => float::fract(&args[0]),
             FloatAbs => float::abs(&args[0]),
@@ -786,8 +883,30 @@ impl<SS, SA, DS, DA, F, Fut> BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut
             IntBitShl => int::bit_shl(&args[0], &args[1]),
             IntBitShr => int::bit_shr(&args[0], &args[1]),
 
-            _ => unimplemented!(),
-        }
+            ArrayConcat => array::concat(&args[0]),
+
+            FunTry => function::try_(&args[0], &args[1], vm),
+
+            ChannelNew => channel::new(&args[0]),
+            ChannelTryRecv => channel::try_recv(&args[0]),
+            ChannelClose => channel::close(&args[0]),
+
+            TimeNow => time::now(),
+
+            SyncMutexNew => sync::mutex_new(),
+            SyncMutexUnlock => sync::mutex_unlock(&args[0]),
+            SyncSemaphoreNew => sync::semaphore_new(&args[0]),
+            SyncSemaphoreRelease => sync::semaphore_release(&args[0]),
+            SyncBarrierNew => sync::barrier_new(&args[0]),
+            SyncEventNew => sync::event_new(),
+            SyncEventSet => sync::event_set(&args[0]),
+
+            GeneratorNew => generator::new(&args[0]),
+            GeneratorClose => generator::close(&args[0]),
+
+            FuelRemaining => fuel::remaining(),
+        };
+        result.map_err(|e| e.in_function(self.clone().into()))
     }
 }
 
@@ -796,14 +915,7 @@ pub enum DynamicCoreFunction {
     Foo,
 }
 
-impl<SS, SA, DS, DA, F, Fut> BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut>, CoreFailure<V<SS, SA, DS, DA, F, Fut>>> for DynamicCoreFunction where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> BuiltInSynchronousFunction<V<C>, CoreFailure<V<C>>> for DynamicCoreFunction {
     fn arity(&self) -> Arity {
         match self {
             _ => unimplemented!(),
@@ -812,22 +924,97 @@ impl<SS, SA, DS, DA, F, Fut> BuiltInSynchronousFunction<V<SS, SA, DS, DA, F, Fut
 
     fn invoke(
         &mut self,
-        args: &[V<SS, SA, DS, DA, F, Fut>],
-        vm: &mut VirtualMachine<V<SS, SA, DS, DA, F, Fut>>,
-    ) -> Result<V<SS, SA, DS, DA, F, Fut>, CoreFailure<V<SS, SA, DS, DA, F, Fut>>> {
+        args: &[V<C>],
+        vm: &mut VirtualMachine<V<C>>,
+    ) -> Result<V<C>, CoreFailure<V<C>>> {
+        check_arity(BuiltInSynchronousFunction::<V<C>, CoreFailure<V<C>>>::arity(self), args)?;
         match self {
             _ => unimplemented!(),
         }
     }
 }
 
-pub enum CoreFailure<Val> {
-    Halt(Val),
-    NotBool(Val),
-    NotFloat(Val),
-    NotInt(Val),
-    NotPositiveInt(Val),
-    NotNonZeroInt(Val),
-    Overflow(i64, i64),
-    Overflow1,
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use guvm_rs::{BuiltInAsyncFunction, BuiltInSynchronousFunction, VirtualMachine};
+
+    use super::{SynchronousFun, AsynchronousFun, SynchronousCoreFunction, AsynchronousCoreFunction};
+    use crate::{V, DefaultConfig, DefaultV, VvvmFailure, VvvmFuture, CoreFailure};
+    use crate::registry::CoreFunction;
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    #[test]
+    fn synchronous_arity_mismatch() {
+        let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+
+        match SynchronousCoreFunction::IntAdd.invoke(&[DefaultV::int(1)], &mut vm) {
+            Err(CoreFailure::ArityMismatch { at, expected: 2, actual: 1 }) => {
+                assert_eq!(at.function, Some(CoreFunction::Synchronous(SynchronousCoreFunction::IntAdd)));
+            }
+            _ => panic!("expected an arity mismatch"),
+        }
+
+        let mut f: SynchronousFun<DefaultConfig> = SynchronousFun::Core(SynchronousCoreFunction::ValueTruthy);
+        match f.invoke(&[], &mut vm) {
+            Err(VvvmFailure::Core(CoreFailure::ArityMismatch { expected: 1, actual: 0, .. })) => {}
+            _ => panic!("expected an arity mismatch"),
+        }
+        match f.invoke(&[V::nil(), V::nil()], &mut vm) {
+            Err(VvvmFailure::Core(CoreFailure::ArityMismatch { expected: 1, actual: 2, .. })) => {}
+            _ => panic!("expected an arity mismatch"),
+        }
+    }
+
+    #[test]
+    fn asynchronous_arity_mismatch() {
+        let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+        let waker = Waker::from(Arc::new(Noop));
+        let mut cx = Context::from_waker(&waker);
+
+        let mut yield_ = AsynchronousCoreFunction::PreemptiveYield;
+        let mut fut = BuiltInAsyncFunction::<DefaultV, VvvmFailure<DefaultConfig>, _>::invoke(&mut yield_, &[V::nil()], &mut vm);
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(Err(CoreFailure::ArityMismatch { expected: 0, actual: 1, .. })) => {}
+            _ => panic!("expected an arity mismatch"),
+        }
+
+        let mut f: AsynchronousFun<DefaultConfig> = AsynchronousFun::Core(AsynchronousCoreFunction::PreemptiveYield);
+        match f.invoke(&[V::nil(), V::nil()], &mut vm) {
+            VvvmFuture::Core(mut fut) => match Pin::new(&mut fut).poll(&mut cx) {
+                Poll::Ready(Err(CoreFailure::ArityMismatch { expected: 0, actual: 2, .. })) => {}
+                _ => panic!("expected an arity mismatch"),
+            },
+            _ => panic!("expected a core future"),
+        }
+    }
+
+    #[test]
+    fn packed_arguments() {
+        let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+
+        let mut min = SynchronousCoreFunction::OrderTotalMin;
+        let args = min.parameters().pack(vec![DefaultV::int(3), V::int(1), V::int(2)]).unwrap();
+        assert_eq!(min.invoke(&args, &mut vm).ok(), Some(V::int(1)));
+        let args = min.parameters().pack(vec![DefaultV::int(3)]).unwrap();
+        assert_eq!(min.invoke(&args, &mut vm).ok(), Some(V::int(3)));
+        assert!(min.parameters().pack::<DefaultConfig>(vec![]).is_none());
+
+        let mut round = SynchronousCoreFunction::FloatRound;
+        let args = round.parameters().pack(vec![DefaultV::float(2.345)]).unwrap();
+        assert_eq!(round.invoke(&args, &mut vm).ok(), Some(V::float(2.0)));
+        let args = round.parameters().pack(vec![DefaultV::float(2.345), V::int(1)]).unwrap();
+        assert_eq!(round.invoke(&args, &mut vm).ok(), Some(V::float(2.3)));
+        assert!(round.parameters().pack(vec![DefaultV::float(2.345), V::int(1), V::nil()]).is_none());
+    }
 }
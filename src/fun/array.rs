@@ -0,0 +1,10 @@
+use super::util::*;
+use crate::{V, VvvmConfig};
+
+fun!(concat(arrays: array) {
+    let mut elements = Vec::new();
+    for array in arrays.iter() {
+        elements.extend(at_arg(0, as_array(array))?.iter().cloned());
+    }
+    Ok(V::Array(elements.into_iter().collect()))
+});
@@ -1,21 +1,19 @@
 use super::util::*;
-use crate::{V, ValueBaseOrdered, ValueBase};
+use crate::{V, VvvmConfig};
 
-fun!(not(b) {
-    Ok(V::boo(!as_bool(b)?))
+fun!(not(b: bool) {
+    Ok(V::boo(!b))
 });
 
-fun!(and(b, c) {
-    Ok(V::boo(as_bool(b)? && as_bool(c)?))
+fun!(and(b: bool, c: bool) {
+    Ok(V::boo(b && c))
 });
 
-fun!(or(b, c) {
-    Ok(V::boo(as_bool(b)? || as_bool(c)?))
+fun!(or(b: bool, c: bool) {
+    Ok(V::boo(b || c))
 });
 
-fun!(if_(b, c) {
-    let b = as_bool(b)?;
-    let c = as_bool(c)?;
+fun!(if_(b: bool, c: bool) {
     if b {
         Ok(V::boo(c))
     } else {
@@ -23,10 +21,10 @@ fun!(if_(b, c) {
     }
 });
 
-fun!(iff(b, c) {
-    Ok(V::boo(as_bool(b)? == as_bool(c)?))
+fun!(iff(b: bool, c: bool) {
+    Ok(V::boo(b == c))
 });
 
-fun!(xor(b, c) {
-    Ok(V::boo(as_bool(b)? != as_bool(c)?))
+fun!(xor(b: bool, c: bool) {
+    Ok(V::boo(b != c))
 });
@@ -0,0 +1,276 @@
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::collections::VecDeque;
+
+use super::util::*;
+use super::CoreFailure;
+use crate::{V, VvvmConfig, Opaque};
+use crate::future::CoreFuture;
+use crate::signature::Type;
+
+pub(crate) const CHANNEL: &str = "channel";
+
+// The payload of channel handles. There is no distinction between the sending and the receiving
+// end, every handle can do both. Buffered values are not traced, see `Opaque`.
+struct Channel<C: VvvmConfig> {
+    buffer: VecDeque<V<C>>,
+    // `None` for unbounded channels.
+    capacity: Option<usize>,
+    closed: bool,
+    // Tasks waiting for space in the buffer, and for values respectively.
+    senders: Vec<Waker>,
+    receivers: Vec<Waker>,
+}
+
+impl<C: VvvmConfig> Channel<C> {
+    fn is_full(&self) -> bool {
+        self.capacity.map_or(false, |capacity| self.buffer.len() >= capacity)
+    }
+}
+
+fn as_channel<C: VvvmConfig>(v: &V<C>) -> Result<&RefCell<Channel<C>>, CoreFailure<V<C>>> {
+    match v {
+        V::Opaque(o) => o.downcast_ref(),
+        _ => None,
+    }.ok_or_else(|| CoreFailure::wrong_type(Type::Opaque(CHANNEL), v.clone()))
+}
+
+fn closed<C: VvvmConfig>() -> V<C> {
+    V::err(V::string("closed"))
+}
+
+fun!(new(capacity) {
+    let capacity = match optional(capacity) {
+        None => None,
+        Some(capacity) => Some(at_arg(0, as_strictly_positive_int(capacity))? as usize),
+    };
+
+    Ok(V::Opaque(Opaque::new(CHANNEL, RefCell::new(Channel::<C> {
+        buffer: VecDeque::new(),
+        capacity,
+        closed: false,
+        senders: Vec::new(),
+        receivers: Vec::new(),
+    }))))
+});
+
+fun!(try_recv(channel) {
+    let mut channel = at_arg(0, as_channel(channel))?.borrow_mut();
+    match channel.buffer.pop_front() {
+        Some(v) => {
+            wake_all(&mut channel.senders);
+            Ok(V::ok(v))
+        }
+        None if channel.closed => Ok(closed()),
+        None => Ok(V::err(V::string("empty"))),
+    }
+});
+
+fun!(close(channel) {
+    let mut channel = at_arg(0, as_channel(channel))?.borrow_mut();
+    channel.closed = true;
+    wake_all(&mut channel.senders);
+    wake_all(&mut channel.receivers);
+    Ok(V::nil())
+});
+
+pub fn send<C: VvvmConfig>(channel: &V<C>, v: &V<C>) -> F<C> {
+    at_arg(0, as_channel(channel))?;
+    Ok(CoreFuture::new(Sending {
+        channel: channel.clone(),
+        value: Some(v.clone()),
+    }))
+}
+
+pub fn recv<C: VvvmConfig>(channel: &V<C>) -> F<C> {
+    at_arg(0, as_channel(channel))?;
+    Ok(CoreFuture::new(Receiving {
+        channel: channel.clone(),
+    }))
+}
+
+// Resolves to `ok(nil)` once the value is in the buffer, or to `err("closed")` if the channel is
+// closed before that.
+struct Sending<C: VvvmConfig> {
+    channel: V<C>,
+    value: Option<V<C>>,
+}
+
+// Neither future is self-referential.
+impl<C: VvvmConfig> Unpin for Sending<C> {}
+
+impl<C: VvvmConfig> Unpin for Receiving<C> {}
+
+impl<C: VvvmConfig> Future for Sending<C> {
+    type Output = Result<V<C>, CoreFailure<V<C>>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let value = self.value.take().expect("Sending polled after completion");
+        let mut channel = as_channel(&self.channel).expect("checked on creation").borrow_mut();
+
+        if channel.closed {
+            Poll::Ready(Ok(closed()))
+        } else if channel.is_full() {
+            add_waker(&mut channel.senders, cx.waker());
+            drop(channel);
+            self.value = Some(value);
+            Poll::Pending
+        } else {
+            channel.buffer.push_back(value);
+            wake_all(&mut channel.receivers);
+            Poll::Ready(Ok(V::ok(V::nil())))
+        }
+    }
+}
+
+// Resolves to `ok(v)` for the next value `v`, or to `err("closed")` if the channel is closed and
+// all buffered values have been received.
+struct Receiving<C: VvvmConfig> {
+    channel: V<C>,
+}
+
+impl<C: VvvmConfig> Future for Receiving<C> {
+    type Output = Result<V<C>, CoreFailure<V<C>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut channel = as_channel(&self.channel).expect("checked on creation").borrow_mut();
+
+        match channel.buffer.pop_front() {
+            Some(v) => {
+                wake_all(&mut channel.senders);
+                Poll::Ready(Ok(V::ok(v)))
+            }
+            None if channel.closed => Poll::Ready(Ok(closed())),
+            None => {
+                add_waker(&mut channel.receivers, cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+    use core::future::Future;
+    use std::rc::Rc;
+
+    use super::{new, try_recv, close, send, recv};
+    use crate::{V, DefaultConfig, DefaultV, VvvmResult};
+    use crate::executor::{Executor, Task};
+
+    fn array(items: Vec<DefaultV>) -> DefaultV {
+        V::Array(items.into_iter().collect())
+    }
+
+    fn closed() -> DefaultV {
+        V::err(V::string("closed"))
+    }
+
+    fn host<F>(f: F) -> Task<DefaultConfig> where
+        F: Future<Output = VvvmResult<DefaultConfig>> + 'static,
+    {
+        Box::pin(f)
+    }
+
+    // Run the tasks in the order they are given, and return their results in that order.
+    fn run_hosts(hosts: Vec<Task<DefaultConfig>>) -> Vec<DefaultV> {
+        let mut executor = Executor::new();
+        let ids: Vec<_> = hosts.into_iter().map(|host| executor.spawn(host)).collect();
+        let mut results = executor.run();
+        assert_eq!(executor.pending(), 0);
+        ids.iter().map(|id| results.remove(id).unwrap().ok().unwrap()).collect()
+    }
+
+    #[test]
+    fn values_arrive_in_order() {
+        let channel: DefaultV = new(&V::nil()).unwrap();
+        let (receiving, sending) = (channel.clone(), channel);
+        let results = run_hosts(vec![
+            host(async move {
+                let mut received = Vec::new();
+                for _ in 0..3 {
+                    received.push(recv(&receiving)?.await?);
+                }
+                Ok(array(received))
+            }),
+            host(async move {
+                for i in 0..3 {
+                    send(&sending, &V::int(i))?.await?;
+                }
+                Ok(V::nil())
+            }),
+        ]);
+        assert_eq!(results[0], array(vec![V::ok(V::int(0)), V::ok(V::int(1)), V::ok(V::int(2))]));
+    }
+
+    #[test]
+    fn bounded_send_waits_for_space() {
+        let channel: DefaultV = new(&V::int(1)).unwrap();
+        let log: Rc<RefCell<Vec<DefaultV>>> = Rc::new(RefCell::new(Vec::new()));
+        let (receiving, sending) = (channel.clone(), channel);
+        let (receiver_log, sender_log) = (log.clone(), log.clone());
+        run_hosts(vec![
+            host(async move {
+                for i in 0..2 {
+                    send(&sending, &V::int(i))?.await?;
+                    sender_log.borrow_mut().push(V::string("sent"));
+                }
+                Ok(V::nil())
+            }),
+            host(async move {
+                for _ in 0..2 {
+                    let v = recv(&receiving)?.await?;
+                    receiver_log.borrow_mut().push(v);
+                }
+                Ok(V::nil())
+            }),
+        ]);
+
+        // The second send only completes once the first value has been received.
+        assert_eq!(*log.borrow(), vec![V::string("sent"), V::ok(V::int(0)), V::string("sent"), V::ok(V::int(1))]);
+    }
+
+    #[test]
+    fn try_recv_does_not_wait() {
+        let results = run_hosts(vec![host(async {
+            let channel = new(&V::nil())?;
+            let empty = try_recv(&channel)?;
+            send(&channel, &V::int(1))?.await?;
+            Ok(array(vec![empty, try_recv(&channel)?, try_recv(&channel)?]))
+        })]);
+        let empty = V::err(V::string("empty"));
+        assert_eq!(results[0], array(vec![empty.clone(), V::ok(V::int(1)), empty]));
+    }
+
+    #[test]
+    fn close_wakes_receivers_after_draining() {
+        let channel: DefaultV = new(&V::nil()).unwrap();
+        let (receiving, closing) = (channel.clone(), channel);
+        let results = run_hosts(vec![
+            host(async move { Ok(recv(&receiving)?.await?) }),
+            host(async move { Ok(close(&closing)?) }),
+        ]);
+        assert_eq!(results[0], closed());
+
+        let results = run_hosts(vec![host(async {
+            let channel = new(&V::int(2))?;
+            send(&channel, &V::int(1))?.await?;
+            close(&channel)?;
+            Ok(array(vec![recv(&channel)?.await?, recv(&channel)?.await?, try_recv(&channel)?]))
+        })]);
+        assert_eq!(results[0], array(vec![V::ok(V::int(1)), closed(), closed()]));
+    }
+
+    #[test]
+    fn send_after_close() {
+        let results = run_hosts(vec![host(async {
+            let channel = new(&V::nil())?;
+            close(&channel)?;
+            Ok(array(vec![send(&channel, &V::int(1))?.await?, try_recv(&channel)?]))
+        })]);
+        assert_eq!(results[0], array(vec![closed(), closed()]));
+    }
+}
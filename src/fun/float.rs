@@ -1,227 +1,189 @@
 use super::util::*;
-use crate::{V, ValueBaseOrdered, ValueBase};
+use crate::{V, VvvmConfig};
 
-fun!(add(x, y) {
-    let x = as_float(x)?;
-    let y = as_float(y)?;
+fun!(add(x: float, y: float) {
     Ok(V::float(x + y))
 });
 
-fun!(sub(x, y) {
-    let x = as_float(x)?;
-    let y = as_float(y)?;
+fun!(sub(x: float, y: float) {
     Ok(V::float(x - y))
 });
 
-fun!(mul(x, y) {
-    let x = as_float(x)?;
-    let y = as_float(y)?;
+fun!(mul(x: float, y: float) {
     Ok(V::float(x * y))
 });
 
-fun!(div(x, y) {
-    let x = as_float(x)?;
-    let y = as_float(y)?;
+fun!(div(x: float, y: float) {
     Ok(V::float(x / y))
 });
 
-fun!(mul_add(x, y, z) {
-    let x = as_float(x)?;
-    let y = as_float(y)?;
-    let z = as_float(z)?;
+fun!(mul_add(x: float, y: float, z: float) {
     Ok(V::float(x.mul_add(y, z)))
 });
 
-fun!(neg(x) {
-    let x = as_float(x)?;
+fun!(neg(x: float) {
     Ok(V::float(-x))
 });
 
-fun!(floor(x) {
-    let x = as_float(x)?;
+fun!(floor(x: float) {
     Ok(V::float(x.floor()))
 });
 
-fun!(ceil(x) {
-    let x = as_float(x)?;
+fun!(ceil(x: float) {
     Ok(V::float(x.ceil()))
 });
 
-fun!(round(x) {
-    let x = as_float(x)?;
-    Ok(V::float(x.round()))
+// Rounds to the given number of decimal digits (negative ones round to tens, hundreds, etc.), or to
+// an integral float if `digits` is left out.
+fun!(round(x: float, digits) {
+    match optional(digits) {
+        None => Ok(V::float(x.round())),
+        Some(digits) => {
+            let digits = at_arg(1, as_int(digits))?;
+            let factor = 10f64.powi(digits.max(-400).min(400) as i32);
+            let scaled = x * factor;
+            if scaled.is_finite() && factor != 0.0 {
+                Ok(V::float(scaled.round() / factor))
+            } else {
+                Ok(V::float(x))
+            }
+        }
+    }
 });
 
-fun!(trunc(x) {
-    let x = as_float(x)?;
+fun!(trunc(x: float) {
     Ok(V::float(x.trunc()))
 });
 
-fun!(fract(x) {
-    let x = as_float(x)?;
+fun!(fract(x: float) {
     Ok(V::float(x.fract()))
 });
 
-fun!(abs(x) {
-    let x = as_float(x)?;
+fun!(abs(x: float) {
     Ok(V::float(x.abs()))
 });
 
-fun!(signum(x) {
-    let x = as_float(x)?;
+fun!(signum(x: float) {
     Ok(V::float(x.signum()))
 });
 
-fun!(pow(x, y) {
-    let x = as_float(x)?;
-    let y = as_float(y)?;
+fun!(pow(x: float, y: float) {
     Ok(V::float(x.powf(y)))
 });
 
-fun!(sqrt(x) {
-    let x = as_float(x)?;
+fun!(sqrt(x: float) {
     Ok(V::float(x.sqrt()))
 });
 
-fun!(exp(x) {
-    let x = as_float(x)?;
+fun!(exp(x: float) {
     Ok(V::float(x.exp()))
 });
 
-fun!(exp2(x) {
-    let x = as_float(x)?;
+fun!(exp2(x: float) {
     Ok(V::float(x.exp2()))
 });
 
-fun!(ln(x) {
-    let x = as_float(x)?;
+fun!(ln(x: float) {
     Ok(V::float(x.ln()))
 });
 
-fun!(log2(x) {
-    let x = as_float(x)?;
+fun!(log2(x: float) {
     Ok(V::float(x.log2()))
 });
 
-fun!(log10(x) {
-    let x = as_float(x)?;
+fun!(log10(x: float) {
     Ok(V::float(x.log10()))
 });
 
-fun!(hypot(x, y) {
-    let x = as_float(x)?;
-    let y = as_float(y)?;
+fun!(hypot(x: float, y: float) {
     Ok(V::float(x.hypot(y)))
 });
 
-fun!(sin(x) {
-    let x = as_float(x)?;
+fun!(sin(x: float) {
     Ok(V::float(x.sin()))
 });
 
-fun!(cos(x) {
-    let x = as_float(x)?;
+fun!(cos(x: float) {
     Ok(V::float(x.cos()))
 });
 
-fun!(tan(x) {
-    let x = as_float(x)?;
+fun!(tan(x: float) {
     Ok(V::float(x.tan()))
 });
 
-fun!(asin(x) {
-    let x = as_float(x)?;
+fun!(asin(x: float) {
     Ok(V::float(x.asin()))
 });
 
-fun!(acos(x) {
-    let x = as_float(x)?;
+fun!(acos(x: float) {
     Ok(V::float(x.acos()))
 });
 
-fun!(atan(x) {
-    let x = as_float(x)?;
+fun!(atan(x: float) {
     Ok(V::float(x.atan()))
 });
 
-fun!(atan2(x, y) {
-    let x = as_float(x)?;
-    let y = as_float(y)?;
+fun!(atan2(x: float, y: float) {
     Ok(V::float(x.atan2(y)))
 });
 
-fun!(exp_m1(x) {
-    let x = as_float(x)?;
+fun!(exp_m1(x: float) {
     Ok(V::float(x.exp_m1()))
 });
 
-fun!(ln_1p(x) {
-    let x = as_float(x)?;
+fun!(ln_1p(x: float) {
     Ok(V::float(x.ln_1p()))
 });
 
-fun!(sinh(x) {
-    let x = as_float(x)?;
+fun!(sinh(x: float) {
     Ok(V::float(x.sinh()))
 });
 
-fun!(cosh(x) {
-    let x = as_float(x)?;
+fun!(cosh(x: float) {
     Ok(V::float(x.cosh()))
 });
 
-fun!(tanh(x) {
-    let x = as_float(x)?;
+fun!(tanh(x: float) {
     Ok(V::float(x.tanh()))
 });
 
-fun!(asinh(x) {
-    let x = as_float(x)?;
+fun!(asinh(x: float) {
     Ok(V::float(x.asinh()))
 });
 
-fun!(acosh(x) {
-    let x = as_float(x)?;
+fun!(acosh(x: float) {
     Ok(V::float(x.acosh()))
 });
 
-fun!(atanh(x) {
-    let x = as_float(x)?;
+fun!(atanh(x: float) {
     Ok(V::float(x.atanh()))
 });
 
-fun!(is_normal(x) {
-    let x = as_float(x)?;
+fun!(is_normal(x: float) {
     Ok(V::boo(x.is_normal()))
 });
 
-fun!(to_degrees(x) {
-    let x = as_float(x)?;
+fun!(to_degrees(x: float) {
     Ok(V::float(x.to_degrees()))
 });
 
-fun!(to_radians(x) {
-    let x = as_float(x)?;
+fun!(to_radians(x: float) {
     Ok(V::float(x.to_radians()))
 });
 
-fun!(to_int(xf) {
-    let x = as_float(xf)?;
-
+fun!(to_int(x: float) {
     if x >= (std::i64::MAX as f64) || x <= (std::i64::MIN as f64) || x.is_nan() {
-        Ok(V::err(xf.clone()))
+        Ok(V::err(V::float(x)))
     } else {
         return Ok(V::int(x as i64));
     }
 });
 
-fun!(from_int(n) {
-    let n = as_int(n)?;
+fun!(from_int(n: int) {
     Ok(V::float(n as f64))
 });
 
-fun!(to_bits(x) {
-    let x = as_float(x)?;
+fun!(to_bits(x: float) {
     if x.is_nan() {
         Ok(V::int(-1))
     } else {
@@ -229,7 +191,6 @@ fun!(to_bits(x) {
     }
 });
 
-fun!(from_bits(n) {
-    let n = as_int(n)?;
+fun!(from_bits(n: int) {
     Ok(V::float(f64::from_bits(n as u64)))
 });
@@ -0,0 +1,20 @@
+use super::util::*;
+use crate::{V, VvvmConfig};
+use crate::fuel::{self, FuelYield};
+use crate::future::CoreFuture;
+
+fun!(remaining() {
+    Ok(fuel::active().map_or(V::nil(), |fuel| V::int(fuel.remaining().min(i64::MAX as u64) as i64)))
+});
+
+pub fn yield_<C: VvvmConfig>(amount: &V<C>) -> F<C> {
+    let wait = FuelYield {
+        fuel: fuel::active(),
+        amount: at_arg(0, as_positive_int(amount))? as u64,
+        yielded: false,
+    };
+    Ok(CoreFuture::new(async move {
+        wait.await;
+        Ok(V::nil())
+    }))
+}
@@ -0,0 +1,90 @@
+use guvm_rs::VirtualMachine;
+
+use super::util::*;
+use super::CoreFailure;
+use crate::{V, VvvmConfig, VvvmFailure};
+
+// Not defined via `fun!`, since it needs access to the virtual machine.
+pub fn try_<C: VvvmConfig>(f: &V<C>, args: &V<C>, vm: &mut VirtualMachine<V<C>>) -> R<C> {
+    let args: Vec<V<C>> = at_arg(1, as_array(args))?.iter().cloned().collect();
+
+    match vm.invoke(f.clone(), &args) {
+        Ok(v) => Ok(V::ok(v)),
+        // Halting is not an error but a request to stop, so it is not caught.
+        Err(VvvmFailure::Core(e @ CoreFailure::Halt { .. })) => Err(e),
+        Err(e) => {
+            let mut entries = e.entries();
+            entries.push((V::string("arguments"), V::Array(args.into_iter().collect())));
+            Ok(V::err(V::Map(entries.into_iter().collect())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use guvm_rs::VirtualMachine;
+
+    use super::try_;
+    use crate::{V, DefaultV, CoreFailure};
+    use crate::registry::CoreFunction;
+    use crate::fun::SynchronousCoreFunction;
+
+    fn core(f: SynchronousCoreFunction) -> DefaultV {
+        CoreFunction::from(f).to_value()
+    }
+
+    fn array(items: Vec<DefaultV>) -> DefaultV {
+        V::Array(items.into_iter().collect())
+    }
+
+    // The entry for `key` of the error map in `result`.
+    fn error_entry(result: &DefaultV, key: &str) -> Option<DefaultV> {
+        match result {
+            V::Map(m) => match m.get(&V::string("err")) {
+                Some(V::Map(e)) => e.get(&V::string(key)).cloned(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn success() {
+        let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+        let result = try_(&core(SynchronousCoreFunction::IntDiv), &array(vec![V::int(7), V::int(2)]), &mut vm);
+        assert_eq!(result.ok(), Some(V::ok(V::int(3))));
+    }
+
+    #[test]
+    fn failure() {
+        let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+        let args = array(vec![V::int(1), V::int(0)]);
+        let result = try_(&core(SynchronousCoreFunction::IntDiv), &args, &mut vm).unwrap();
+
+        assert_eq!(error_entry(&result, "kind"), Some(V::string("wrong_type")));
+        assert_eq!(error_entry(&result, "function"), Some(V::string("int_div")));
+        assert_eq!(error_entry(&result, "argument"), Some(V::int(1)));
+        assert_eq!(error_entry(&result, "expected"), Some(V::string("non_zero_int")));
+        assert_eq!(error_entry(&result, "value"), Some(V::int(0)));
+        assert_eq!(error_entry(&result, "arguments"), Some(args));
+        assert!(error_entry(&result, "message").is_some());
+    }
+
+    #[test]
+    fn halting_is_not_caught() {
+        let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+        match try_(&core(SynchronousCoreFunction::ValueHalt), &array(vec![V::int(42)]), &mut vm) {
+            Err(CoreFailure::Halt { value, .. }) => assert_eq!(value, V::int(42)),
+            _ => panic!("expected the halt to propagate"),
+        }
+    }
+
+    #[test]
+    fn arguments_must_be_an_array() {
+        let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+        match try_(&core(SynchronousCoreFunction::IntAdd), &V::int(1), &mut vm) {
+            Err(e @ CoreFailure::WrongType { .. }) => assert_eq!(e.at().arg, Some(1)),
+            _ => panic!("expected a wrong type of the arguments"),
+        }
+    }
+}
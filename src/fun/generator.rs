@@ -0,0 +1,355 @@
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use super::util::*;
+use super::CoreFailure;
+use crate::{V, VvvmConfig, Opaque};
+use crate::executor::{self, Join};
+use crate::future::CoreFuture;
+use crate::signature::Type;
+
+pub(crate) const GENERATOR: &str = "generator";
+
+// The payload of generator handles. The producer runs as a task that is only spawned once the
+// first value is requested, and it only computes a value once the previous one has been taken.
+// The closure and the slot are not traced, see `Opaque`.
+struct Generator<C: VvvmConfig> {
+    // The producing closure, until it is started.
+    f: Option<V<C>>,
+    // The producer task, once started.
+    producer: Option<Join<C>>,
+    // Whether the consumer waits for a value the producer has not yielded yet.
+    demand: bool,
+    // A yielded value that the consumer has not taken yet.
+    slot: Option<V<C>>,
+    // What `generator_next` returns once the producer has finished.
+    finished: Option<Step<C>>,
+    producer_waker: Option<Waker>,
+    consumer_waker: Option<Waker>,
+}
+
+// What the consumer gets out of the generator.
+enum Step<C: VvvmConfig> {
+    Value(V<C>),
+    Done,
+    // The producer failed, the failure as a value.
+    Failed(V<C>),
+}
+
+impl<C: VvvmConfig> Clone for Step<C> {
+    fn clone(&self) -> Self {
+        match self {
+            Step::Value(v) => Step::Value(v.clone()),
+            Step::Done => Step::Done,
+            Step::Failed(e) => Step::Failed(e.clone()),
+        }
+    }
+}
+
+impl<C: VvvmConfig> Step<C> {
+    fn to_value(self) -> V<C> {
+        match self {
+            Step::Value(v) => V::ok(v),
+            Step::Done => V::err(V::string("done")),
+            Step::Failed(e) => V::err(e),
+        }
+    }
+}
+
+fn as_generator<C: VvvmConfig>(v: &V<C>) -> Result<&RefCell<Generator<C>>, CoreFailure<V<C>>> {
+    match v {
+        V::Opaque(o) => o.downcast_ref(),
+        _ => None,
+    }.ok_or_else(|| CoreFailure::wrong_type(Type::Opaque(GENERATOR), v.clone()))
+}
+
+fun!(new(f) {
+    Ok(V::Opaque(Opaque::new(GENERATOR, RefCell::new(Generator {
+        f: Some(f.clone()),
+        producer: None,
+        demand: false,
+        slot: None,
+        finished: None,
+        producer_waker: None,
+        consumer_waker: None,
+    }))))
+});
+
+fun!(close(generator) {
+    let producer = {
+        let mut generator = at_arg(0, as_generator(generator))?.borrow_mut();
+        generator.f = None;
+        generator.slot = None;
+        if generator.finished.is_none() {
+            generator.finished = Some(Step::Done);
+        }
+        if let Some(waker) = generator.consumer_waker.take() {
+            waker.wake();
+        }
+        generator.producer.take()
+    };
+
+    // Outside the borrow, since cancelling drops the producer's pending `generator_yield`.
+    if let Some(producer) = producer {
+        producer.cancel();
+    }
+    Ok(V::nil())
+});
+
+pub fn yield_<C: VvvmConfig>(generator: &V<C>, v: &V<C>) -> F<C> {
+    at_arg(0, as_generator(generator))?;
+    Ok(CoreFuture::new(Yield {
+        handle: generator.clone(),
+        value: Some(v.clone()),
+    }))
+}
+
+pub fn next<C: VvvmConfig>(generator: &V<C>) -> F<C> {
+    at_arg(0, as_generator(generator))?;
+    let next = Next {
+        handle: generator.clone(),
+    };
+    Ok(CoreFuture::new(async move { Ok(next.await?.to_value()) }))
+}
+
+pub fn collect<C: VvvmConfig>(generator: &V<C>) -> F<C> {
+    at_arg(0, as_generator(generator))?;
+    Ok(pull(generator.clone(), None))
+}
+
+pub fn take<C: VvvmConfig>(generator: &V<C>, n: &V<C>) -> F<C> {
+    at_arg(0, as_generator(generator))?;
+    let n = at_arg(1, as_positive_int(n))?;
+    Ok(pull(generator.clone(), Some(n as usize)))
+}
+
+// Resolves to `ok(values)` with the next values of the generator, at most `limit` of them, or to
+// `err(failure)` if the producer fails.
+fn pull<C: VvvmConfig>(handle: V<C>, limit: Option<usize>) -> CoreFuture<V<C>> {
+    CoreFuture::new(async move {
+        let mut values = Vec::new();
+        while limit.map_or(true, |limit| values.len() < limit) {
+            match (Next { handle: handle.clone() }).await? {
+                Step::Value(v) => values.push(v),
+                Step::Done => break,
+                Step::Failed(e) => return Ok(V::err(e)),
+            }
+        }
+        Ok(V::ok(V::Array(values.into_iter().collect())))
+    })
+}
+
+// Hands the value to the consumer once it asks for one, then resolves to `nil` when the consumer
+// asks for the next one.
+struct Yield<C: VvvmConfig> {
+    handle: V<C>,
+    // `None` once handed over.
+    value: Option<V<C>>,
+}
+
+// Resolves to the next step of the generator, starting the producer if necessary.
+struct Next<C: VvvmConfig> {
+    handle: V<C>,
+}
+
+// Neither future is self-referential.
+impl<C: VvvmConfig> Unpin for Yield<C> {}
+
+impl<C: VvvmConfig> Unpin for Next<C> {}
+
+impl<C: VvvmConfig> Future for Yield<C> {
+    type Output = Result<V<C>, CoreFailure<V<C>>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut generator = as_generator(&this.handle).expect("checked on creation").borrow_mut();
+
+        if this.value.is_some() && generator.demand {
+            generator.slot = this.value.take();
+            generator.demand = false;
+            if let Some(waker) = generator.consumer_waker.take() {
+                waker.wake();
+            }
+        }
+
+        if generator.finished.is_some() || (this.value.is_none() && generator.demand) {
+            Poll::Ready(Ok(V::nil()))
+        } else {
+            generator.producer_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl<C: VvvmConfig> Future for Next<C> {
+    type Output = Result<Step<C>, CoreFailure<V<C>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let generator = as_generator(&self.handle).expect("checked on creation");
+
+        {
+            let mut generator = generator.borrow_mut();
+            if let Some(v) = generator.slot.take() {
+                return Poll::Ready(Ok(Step::Value(v)));
+            }
+            if let Some(finished) = &generator.finished {
+                return Poll::Ready(Ok(finished.clone()));
+            }
+        }
+
+        // Not borrowed while spawning, the producer receives the generator as its argument.
+        let f = generator.borrow_mut().f.take();
+        if let Some(f) = f {
+            match executor::spawn_child(f.clone(), vec![self.handle.clone()]) {
+                Ok(task) => generator.borrow_mut().producer = Join::new(task),
+                Err(e) => {
+                    // Not started after all, a later request can try again.
+                    generator.borrow_mut().f = Some(f);
+                    return Poll::Ready(Err(e));
+                }
+            }
+        }
+
+        let poll = {
+            let mut generator = generator.borrow_mut();
+            generator.demand = true;
+            generator.consumer_waker = Some(cx.waker().clone());
+            if let Some(waker) = generator.producer_waker.take() {
+                waker.wake();
+            }
+            Pin::new(generator.producer.as_mut().expect("a started generator")).poll(cx)
+        };
+
+        match poll {
+            Poll::Ready(outcome) => {
+                let finished = match outcome {
+                    Ok(_) => Step::Done,
+                    Err(e) => Step::Failed(e),
+                };
+                generator.borrow_mut().finished = Some(finished.clone());
+                Poll::Ready(Ok(finished))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+
+    use super::{new, next, collect, take, yield_};
+    use crate::{V, DefaultConfig, DefaultV, VvvmFailure, VvvmResult, CoreFailure};
+    use crate::executor::{Executor, Task};
+
+    fn array(items: Vec<DefaultV>) -> DefaultV {
+        V::Array(items.into_iter().collect())
+    }
+
+    fn done() -> DefaultV {
+        V::err(V::string("done"))
+    }
+
+    // Producers of an int `n` yield the ints from zero up to `n`, or forever if `n` is negative.
+    // All other producers fail.
+    fn run(f: DefaultV, args: Vec<DefaultV>) -> Task<DefaultConfig> {
+        Box::pin(async move {
+            let count = match f {
+                V::Int(n) => n,
+                _ => return Err(VvvmFailure::Core(CoreFailure::halt(f))),
+            };
+            let mut i = 0;
+            while count < 0 || i < count {
+                yield_(&args[0], &V::int(i))?.await?;
+                i += 1;
+            }
+            Ok(V::nil())
+        })
+    }
+
+    fn run_host<F>(with_runner: bool, f: F) -> VvvmResult<DefaultConfig> where
+        F: Future<Output = VvvmResult<DefaultConfig>> + 'static,
+    {
+        let mut executor = Executor::new();
+        if with_runner {
+            executor.set_runner(run);
+        }
+        let id = executor.spawn(f);
+        executor.run().remove(&id).expect("a finished host task")
+    }
+
+    #[test]
+    fn next_until_done() {
+        let result = run_host(true, async {
+            let generator = new(&V::int(2))?;
+            let mut steps = Vec::new();
+            for _ in 0..4 {
+                steps.push(next(&generator)?.await?);
+            }
+            Ok(array(steps))
+        });
+        assert_eq!(result.ok(), Some(array(vec![V::ok(V::int(0)), V::ok(V::int(1)), done(), done()])));
+    }
+
+    #[test]
+    fn collect_and_take() {
+        let result = run_host(true, async {
+            let finite = new(&V::int(3))?;
+            let infinite = new(&V::int(-1))?;
+            Ok(array(vec![
+                collect(&finite)?.await?,
+                take(&infinite, &V::int(2))?.await?,
+                next(&infinite)?.await?,
+                take(&new(&V::int(1))?, &V::int(5))?.await?,
+            ]))
+        });
+        assert_eq!(result.ok(), Some(array(vec![
+            V::ok(array(vec![V::int(0), V::int(1), V::int(2)])),
+            V::ok(array(vec![V::int(0), V::int(1)])),
+            V::ok(V::int(2)),
+            V::ok(array(vec![V::int(0)])),
+        ])));
+    }
+
+    #[test]
+    fn taking_registers_one_joiner() {
+        let mut executor = Executor::new();
+        executor.set_runner(run);
+        let id = executor.spawn(async {
+            let infinite = new(&V::int(-1))?;
+            take(&infinite, &V::int(1000))?.await?;
+            Ok(infinite)
+        });
+
+        executor.run_until_stalled();
+        assert!(executor.take_result(id).unwrap().is_ok());
+        assert!(executor.joiners() <= 1);
+    }
+
+    #[test]
+    fn failing_producer() {
+        let result = run_host(true, async {
+            let generator = new(&V::boo(false))?;
+            Ok(array(vec![next(&generator)?.await?, collect(&generator)?.await?]))
+        });
+        let failure = V::err(CoreFailure::halt(V::boo(false)).to_value());
+        assert_eq!(result.ok(), Some(array(vec![failure.clone(), failure])));
+    }
+
+    #[test]
+    fn failing_to_start() {
+        let result = run_host(false, async {
+            let generator: DefaultV = new(&V::int(1))?;
+            for _ in 0..2 {
+                match next(&generator)?.await {
+                    Err(CoreFailure::NoExecutor { .. }) => {}
+                    _ => panic!("expected a failure to start the producer"),
+                }
+            }
+            Ok(V::nil())
+        });
+        assert!(result.is_ok());
+    }
+}
@@ -1,328 +1,247 @@
 use super::util::*;
-use crate::{V, ValueBaseOrdered, ValueBase, CoreFailure};
+use crate::{V, VvvmConfig, CoreFailure};
 
-fun!(signum(n) {
-    let n = as_int(n)?;
+fun!(signum(n: int) {
     Ok(V::int(n.signum()))
 });
 
-fun!(add(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
+fun!(add(n: int, m: int) {
     match n.checked_add(m) {
         Some(yay) => Ok(V::int(yay)),
-        None => Err(CoreFailure::Overflow(n, m)),
+        None => Err(CoreFailure::overflow(vec![n, m])),
     }
 });
 
-fun!(sub(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
+fun!(sub(n: int, m: int) {
     match n.checked_sub(m) {
         Some(yay) => Ok(V::int(yay)),
-        None => Err(CoreFailure::Overflow(n, m)),
+        None => Err(CoreFailure::overflow(vec![n, m])),
     }
 });
 
-fun!(mul(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
-    match n.checked_add(m) {
+fun!(mul(n: int, m: int) {
+    match n.checked_mul(m) {
         Some(yay) => Ok(V::int(yay)),
-        None => Err(CoreFailure::Overflow(n, m)),
+        None => Err(CoreFailure::overflow(vec![n, m])),
     }
 });
 
-fun!(div(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(div(n: int, m: non_zero_int) {
     match n.checked_div_euclid(m) {
         Some(yay) => Ok(V::int(yay)),
-        None => Err(CoreFailure::Overflow(n, m)),
+        None => Err(CoreFailure::overflow(vec![n, m])),
     }
 });
 
-fun!(div_trunc(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(div_trunc(n: int, m: non_zero_int) {
     match n.checked_div(m) {
         Some(yay) => Ok(V::int(yay)),
-        None => Err(CoreFailure::Overflow(n, m)),
+        None => Err(CoreFailure::overflow(vec![n, m])),
     }
 });
 
-fun!(mod_(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(mod_(n: int, m: non_zero_int) {
     match n.checked_rem_euclid(m) {
         Some(yay) => Ok(V::int(yay)),
-        None => Err(CoreFailure::Overflow(n, m)),
+        None => Err(CoreFailure::overflow(vec![n, m])),
     }
 });
 
-fun!(mod_trunc(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(mod_trunc(n: int, m: non_zero_int) {
     match n.checked_rem(m) {
         Some(yay) => Ok(V::int(yay)),
-        None => Err(CoreFailure::Overflow(n, m)),
+        None => Err(CoreFailure::overflow(vec![n, m])),
     }
 });
 
-fun!(neg(n) {
-    let n = as_int(n)?;
-
+fun!(neg(n: int) {
     match n.checked_neg() {
         Some(yay) => Ok(V::int(yay)),
-        None => Err(CoreFailure::Overflow1),
+        None => Err(CoreFailure::overflow(vec![n])),
     }
 });
 
-fun!(abs(n) {
-    let n = as_int(n)?;
-
+fun!(abs(n: int) {
     match n.checked_abs() {
         Some(yay) => Ok(V::int(yay)),
-        None => Err(CoreFailure::Overflow1),
+        None => Err(CoreFailure::overflow(vec![n])),
     }
 });
 
-fun!(pow(n, m) {
-    let n = as_int(n)?;
-    let m = as_positive_int(m)?;
+fun!(pow(n: int, m: positive_int) {
     match n.checked_pow(m as u32) {
         Some(yay) => Ok(V::int(yay)),
-        None => Err(CoreFailure::Overflow(n, m)),
+        None => Err(CoreFailure::overflow(vec![n, m])),
     }
 });
 
-fun!(check_add(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
+fun!(check_add(n: int, m: int) {
     match n.checked_add(m) {
         Some(yay) => Ok(V::int(yay)),
         None => Ok(V::err_nil()),
     }
 });
 
-fun!(check_sub(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
+fun!(check_sub(n: int, m: int) {
     match n.checked_sub(m) {
         Some(yay) => Ok(V::int(yay)),
         None => Ok(V::err_nil()),
     }
 });
 
-fun!(check_mul(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
-    match n.checked_add(m) {
+fun!(check_mul(n: int, m: int) {
+    match n.checked_mul(m) {
         Some(yay) => Ok(V::int(yay)),
         None => Ok(V::err_nil()),
     }
 });
 
-fun!(check_div(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(check_div(n: int, m: non_zero_int) {
     match n.checked_div_euclid(m) {
         Some(yay) => Ok(V::int(yay)),
         None => Ok(V::err_nil()),
     }
 });
 
-fun!(check_div_trunc(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(check_div_trunc(n: int, m: non_zero_int) {
     match n.checked_div(m) {
         Some(yay) => Ok(V::int(yay)),
         None => Ok(V::err_nil()),
     }
 });
 
-fun!(check_mod_(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(check_mod_(n: int, m: non_zero_int) {
     match n.checked_rem_euclid(m) {
         Some(yay) => Ok(V::int(yay)),
         None => Ok(V::err_nil()),
     }
 });
 
-fun!(check_mod_trunc(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(check_mod_trunc(n: int, m: non_zero_int) {
     match n.checked_rem(m) {
         Some(yay) => Ok(V::int(yay)),
         None => Ok(V::err_nil()),
     }
 });
 
-fun!(check_neg(n) {
-    let n = as_int(n)?;
-
+fun!(check_neg(n: int) {
     match n.checked_neg() {
         Some(yay) => Ok(V::int(yay)),
         None => Ok(V::err_nil()),
     }
 });
 
-fun!(check_abs(n) {
-    let n = as_int(n)?;
-
+fun!(check_abs(n: int) {
     match n.checked_abs() {
         Some(yay) => Ok(V::int(yay)),
         None => Ok(V::err_nil()),
     }
 });
 
-fun!(check_pow(n, m) {
-    let n = as_int(n)?;
-    let m = as_positive_int(m)?;
+fun!(check_pow(n: int, m: positive_int) {
     match n.checked_pow(m as u32) {
         Some(yay) => Ok(V::int(yay)),
         None => Ok(V::err_nil()),
     }
 });
 
-fun!(sat_add(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
+fun!(sat_add(n: int, m: int) {
     Ok(V::int(n.saturating_add(m)))
 });
 
-fun!(sat_sub(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
+fun!(sat_sub(n: int, m: int) {
     Ok(V::int(n.saturating_sub(m)))
 });
 
-fun!(sat_mul(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
+fun!(sat_mul(n: int, m: int) {
     Ok(V::int(n.saturating_mul(m)))
 });
 
-fun!(sat_pow(n, m) {
-    let n = as_int(n)?;
-    let m = as_positive_int(m)?;
+fun!(sat_pow(n: int, m: positive_int) {
     Ok(V::int(n.saturating_pow(m as u32)))
 });
 
-fun!(wrap_add(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
+fun!(wrap_add(n: int, m: int) {
     Ok(V::int(n.wrapping_add(m)))
 });
 
-fun!(wrap_sub(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
+fun!(wrap_sub(n: int, m: int) {
     Ok(V::int(n.wrapping_sub(m)))
 });
 
-fun!(wrap_mul(n, m) {
-    let n = as_int(n)?;
-    let m = as_int(m)?;
+fun!(wrap_mul(n: int, m: int) {
     Ok(V::int(n.wrapping_mul(m)))
 });
 
-fun!(wrap_div(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(wrap_div(n: int, m: non_zero_int) {
     Ok(V::int(n.wrapping_div_euclid(m)))
 });
 
-fun!(wrap_div_trunc(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(wrap_div_trunc(n: int, m: non_zero_int) {
     Ok(V::int(n.wrapping_div(m)))
 });
 
-fun!(wrap_mod(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(wrap_mod(n: int, m: non_zero_int) {
     Ok(V::int(n.wrapping_rem_euclid(m)))
 });
 
-fun!(wrap_mod_trunc(n, m) {
-    let n = as_int(n)?;
-    let m = as_non_zero_int(m)?;
+fun!(wrap_mod_trunc(n: int, m: non_zero_int) {
     Ok(V::int(n.wrapping_rem(m)))
 });
 
-fun!(wrap_neg(n) {
-    let n = as_int(n)?;
+fun!(wrap_neg(n: int) {
     Ok(V::int(n.wrapping_neg()))
 });
 
-fun!(wrap_abs(n) {
-    let n = as_int(n)?;
+fun!(wrap_abs(n: int) {
     Ok(V::int(n.wrapping_abs()))
 });
 
-fun!(wrap_pow(n, m) {
-    let n = as_int(n)?;
-    let m = as_positive_int(m)?;
+fun!(wrap_pow(n: int, m: positive_int) {
     Ok(V::int(n.wrapping_pow(m as u32)))
 });
 
-fun!(bit_count_ones(n) {
-    let n = as_int(n)?;
+fun!(bit_count_ones(n: int) {
     Ok(V::int(n.count_ones() as i64))
 });
 
-fun!(bit_count_zeros(n) {
-    let n = as_int(n)?;
+fun!(bit_count_zeros(n: int) {
     Ok(V::int(n.count_zeros() as i64))
 });
 
-fun!(bit_leading_ones(n) {
-    let n = as_int(n)?;
+fun!(bit_leading_ones(n: int) {
     Ok(V::int(n.leading_ones() as i64))
 });
 
-fun!(bit_leading_zeros(n) {
-    let n = as_int(n)?;
+fun!(bit_leading_zeros(n: int) {
     Ok(V::int(n.leading_zeros() as i64))
 });
 
-fun!(bit_trailing_ones(n) {
-    let n = as_int(n)?;
+fun!(bit_trailing_ones(n: int) {
     Ok(V::int(n.trailing_ones() as i64))
 });
 
-fun!(bit_trailing_zeros(n) {
-    let n = as_int(n)?;
+fun!(bit_trailing_zeros(n: int) {
     Ok(V::int(n.trailing_zeros() as i64))
 });
 
-fun!(bit_rotate_left(n, m) {
-    let n = as_int(n)?;
-    let m = as_positive_int(m)?;
+fun!(bit_rotate_left(n: int, m: positive_int) {
     Ok(V::int(n.rotate_left(m as u32)))
 });
 
-fun!(bit_rotate_right(n, m) {
-    let n = as_int(n)?;
-    let m = as_positive_int(m)?;
+fun!(bit_rotate_right(n: int, m: positive_int) {
     Ok(V::int(n.rotate_right(m as u32)))
 });
 
-fun!(bit_reverse_bytes(n) {
-    let n = as_int(n)?;
+fun!(bit_reverse_bytes(n: int) {
     Ok(V::int(n.swap_bytes()))
 });
 
-fun!(bit_reverse_bits(n) {
-    let n = as_int(n)?;
+fun!(bit_reverse_bits(n: int) {
     Ok(V::int(n.reverse_bits()))
 });
 
-fun!(bit_shl(n, m) {
-    let n = as_int(n)?;
-    let m = as_positive_int(m)?;
+fun!(bit_shl(n: int, m: positive_int) {
     if m >= 64 {
         Ok(V::int(0))
     } else {
@@ -330,12 +249,26 @@ fun!(bit_shl(n, m) {
     }
 });
 
-fun!(bit_shr(n, m) {
-    let n = as_int(n)?;
-    let m = as_positive_int(m)?;
+fun!(bit_shr(n: int, m: positive_int) {
     if m >= 64 {
         Ok(V::int(0))
     } else {
         Ok(V::int(n >> m))
     }
 });
+
+#[cfg(test)]
+mod tests {
+    use super::{mul, check_mul};
+    use crate::{V, DefaultConfig};
+
+    #[test]
+    fn multiplication() {
+        assert_eq!(mul::<DefaultConfig>(&V::int(21), &V::int(2)).ok(), Some(V::int(42)));
+        assert_eq!(mul::<DefaultConfig>(&V::int(-3), &V::int(5)).ok(), Some(V::int(-15)));
+        assert!(mul::<DefaultConfig>(&V::int(i64::MAX), &V::int(2)).is_err());
+
+        assert_eq!(check_mul::<DefaultConfig>(&V::int(21), &V::int(2)).ok(), Some(V::int(42)));
+        assert_eq!(check_mul::<DefaultConfig>(&V::int(i64::MAX), &V::int(2)).ok(), Some(V::err_nil()));
+    }
+}
@@ -4,7 +4,7 @@ use guvm_rs::Value;
 
 use super::util::*;
 use super::CoreFailure;
-use crate::{V, ValueBaseOrdered, ValueBase};
+use crate::{V, VvvmConfig};
 
 fun!(total_compare(v, w) {
     Ok(match v.cmp(w) {
@@ -38,12 +38,20 @@ fun!(total_neq(v, w) {
     Ok(V::boo(v != w))
 });
 
-fun!(total_min(v, w) {
-    Ok(core::cmp::min(v, w).clone())
+fun!(total_min(v, vs: array) {
+    let mut min = v;
+    for w in vs.iter() {
+        min = core::cmp::min(min, w);
+    }
+    Ok(min.clone())
 });
 
-fun!(total_max(v, w) {
-    Ok(core::cmp::max(v, w).clone())
+fun!(total_max(v, vs: array) {
+    let mut max = v;
+    for w in vs.iter() {
+        max = core::cmp::max(max, w);
+    }
+    Ok(max.clone())
 });
 
 fun!(partial_compare(v, w) {
@@ -0,0 +1,367 @@
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::collections::VecDeque;
+
+use super::util::*;
+use super::CoreFailure;
+use crate::{V, VvvmConfig, Opaque};
+use crate::future::CoreFuture;
+use crate::signature::Type;
+
+pub(crate) const MUTEX: &str = "mutex";
+pub(crate) const SEMAPHORE: &str = "semaphore";
+pub(crate) const BARRIER: &str = "barrier";
+pub(crate) const EVENT: &str = "event";
+
+// The payload of mutexes and semaphores, a mutex being a semaphore with a single permit. Permits
+// are handed out in the order in which tasks started waiting for them.
+struct Semaphore {
+    permits: usize,
+    // Waiting tasks, identified so that they can leave the queue when cancelled.
+    waiters: VecDeque<(u64, Waker)>,
+    next_waiter: u64,
+}
+
+impl Semaphore {
+    fn new(permits: usize) -> Self {
+        Semaphore {
+            permits,
+            waiters: VecDeque::new(),
+            next_waiter: 0,
+        }
+    }
+
+    // Let the first waiting task try again if there is a permit for it.
+    fn wake_first(&self) {
+        if self.permits > 0 {
+            if let Some((_, waker)) = self.waiters.front() {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+struct Barrier {
+    size: usize,
+    // The number of tasks waiting in the current generation.
+    arrived: usize,
+    generation: u64,
+    waiters: Vec<Waker>,
+}
+
+struct Event {
+    set: bool,
+    waiters: Vec<Waker>,
+}
+
+fn as_primitive<'a, T: 'static, C: VvvmConfig>(v: &'a V<C>, kind: &'static str) -> Result<&'a RefCell<T>, CoreFailure<V<C>>> {
+    match v {
+        V::Opaque(o) if o.kind() == kind => o.downcast_ref(),
+        _ => None,
+    }.ok_or_else(|| CoreFailure::wrong_type(Type::Opaque(kind), v.clone()))
+}
+
+fun!(mutex_new() {
+    Ok(V::Opaque(Opaque::new(MUTEX, RefCell::new(Semaphore::new(1)))))
+});
+
+fun!(mutex_unlock(handle) {
+    let mut mutex = at_arg(0, as_primitive::<Semaphore, C>(handle, MUTEX))?.borrow_mut();
+    if mutex.permits > 0 {
+        return Err(CoreFailure::not_locked(handle.clone()).at_arg(0));
+    }
+    mutex.permits = 1;
+    mutex.wake_first();
+    Ok(V::nil())
+});
+
+fun!(semaphore_new(permits: positive_int) {
+    Ok(V::Opaque(Opaque::new(SEMAPHORE, RefCell::new(Semaphore::new(permits as usize)))))
+});
+
+fun!(semaphore_release(semaphore) {
+    let mut semaphore = at_arg(0, as_primitive::<Semaphore, C>(semaphore, SEMAPHORE))?.borrow_mut();
+    semaphore.permits += 1;
+    semaphore.wake_first();
+    Ok(V::nil())
+});
+
+fun!(barrier_new(size: strictly_positive_int) {
+    Ok(V::Opaque(Opaque::new(BARRIER, RefCell::new(Barrier {
+        size: size as usize,
+        arrived: 0,
+        generation: 0,
+        waiters: Vec::new(),
+    }))))
+});
+
+fun!(event_new() {
+    Ok(V::Opaque(Opaque::new(EVENT, RefCell::new(Event {
+        set: false,
+        waiters: Vec::new(),
+    }))))
+});
+
+fun!(event_set(event) {
+    let mut event = at_arg(0, as_primitive::<Event, C>(event, EVENT))?.borrow_mut();
+    let was_set = event.set;
+    event.set = true;
+    wake_all(&mut event.waiters);
+    Ok(V::boo(!was_set))
+});
+
+pub fn mutex_lock<C: VvvmConfig>(mutex: &V<C>) -> F<C> {
+    acquire(mutex, MUTEX)
+}
+
+pub fn semaphore_acquire<C: VvvmConfig>(semaphore: &V<C>) -> F<C> {
+    acquire(semaphore, SEMAPHORE)
+}
+
+fn acquire<C: VvvmConfig>(handle: &V<C>, kind: &'static str) -> F<C> {
+    at_arg(0, as_primitive::<Semaphore, C>(handle, kind))?;
+    Ok(CoreFuture::new(Acquire {
+        handle: handle.clone(),
+        kind,
+        waiter: None,
+    }))
+}
+
+pub fn barrier_wait<C: VvvmConfig>(barrier: &V<C>) -> F<C> {
+    at_arg(0, as_primitive::<Barrier, C>(barrier, BARRIER))?;
+    Ok(CoreFuture::new(BarrierWait {
+        handle: barrier.clone(),
+        generation: None,
+    }))
+}
+
+pub fn event_wait<C: VvvmConfig>(event: &V<C>) -> F<C> {
+    at_arg(0, as_primitive::<Event, C>(event, EVENT))?;
+    Ok(CoreFuture::new(EventWait {
+        handle: event.clone(),
+    }))
+}
+
+// Resolves to `nil` once it has taken a permit. Leaves the queue of waiting tasks when dropped, so
+// cancelling a waiting task never loses a permit.
+struct Acquire<C: VvvmConfig> {
+    handle: V<C>,
+    kind: &'static str,
+    // Set while in the queue of waiting tasks.
+    waiter: Option<u64>,
+}
+
+// Resolves to `true` in exactly one of the tasks of each generation, `false` in the others. A
+// task that is cancelled while waiting no longer counts towards the barrier.
+struct BarrierWait<C: VvvmConfig> {
+    handle: V<C>,
+    // The generation the task is waiting in, once it has arrived.
+    generation: Option<u64>,
+}
+
+struct EventWait<C: VvvmConfig> {
+    handle: V<C>,
+}
+
+// None of the futures are self-referential.
+impl<C: VvvmConfig> Unpin for Acquire<C> {}
+
+impl<C: VvvmConfig> Unpin for BarrierWait<C> {}
+
+impl<C: VvvmConfig> Unpin for EventWait<C> {}
+
+impl<C: VvvmConfig> Future for Acquire<C> {
+    type Output = Result<V<C>, CoreFailure<V<C>>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut semaphore = as_primitive::<Semaphore, C>(&this.handle, this.kind).expect("checked on creation").borrow_mut();
+
+        let is_first = semaphore.waiters.front().map_or(true, |(id, _)| Some(*id) == this.waiter);
+        if semaphore.permits > 0 && is_first {
+            semaphore.permits -= 1;
+            if this.waiter.take().is_some() {
+                semaphore.waiters.pop_front();
+            }
+            semaphore.wake_first();
+            return Poll::Ready(Ok(V::nil()));
+        }
+
+        match this.waiter {
+            Some(id) => {
+                if let Some(entry) = semaphore.waiters.iter_mut().find(|(waiter, _)| *waiter == id) {
+                    entry.1 = cx.waker().clone();
+                }
+            }
+            None => {
+                let id = semaphore.next_waiter;
+                semaphore.next_waiter += 1;
+                semaphore.waiters.push_back((id, cx.waker().clone()));
+                this.waiter = Some(id);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<C: VvvmConfig> Drop for Acquire<C> {
+    fn drop(&mut self) {
+        if let (Some(id), Ok(semaphore)) = (self.waiter, as_primitive::<Semaphore, C>(&self.handle, self.kind)) {
+            let mut semaphore = semaphore.borrow_mut();
+            semaphore.waiters.retain(|(waiter, _)| *waiter != id);
+            semaphore.wake_first();
+        }
+    }
+}
+
+impl<C: VvvmConfig> Future for BarrierWait<C> {
+    type Output = Result<V<C>, CoreFailure<V<C>>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut barrier = as_primitive::<Barrier, C>(&this.handle, BARRIER).expect("checked on creation").borrow_mut();
+
+        match this.generation {
+            None => {
+                barrier.arrived += 1;
+                if barrier.arrived >= barrier.size {
+                    barrier.arrived = 0;
+                    barrier.generation += 1;
+                    wake_all(&mut barrier.waiters);
+                    return Poll::Ready(Ok(V::boo(true)));
+                }
+                this.generation = Some(barrier.generation);
+            }
+            Some(generation) if generation != barrier.generation => {
+                this.generation = None;
+                return Poll::Ready(Ok(V::boo(false)));
+            }
+            Some(_) => {}
+        }
+
+        add_waker(&mut barrier.waiters, cx.waker());
+        Poll::Pending
+    }
+}
+
+impl<C: VvvmConfig> Drop for BarrierWait<C> {
+    fn drop(&mut self) {
+        if let (Some(generation), Ok(barrier)) = (self.generation, as_primitive::<Barrier, C>(&self.handle, BARRIER)) {
+            let mut barrier = barrier.borrow_mut();
+            if barrier.generation == generation {
+                barrier.arrived -= 1;
+            }
+        }
+    }
+}
+
+impl<C: VvvmConfig> Future for EventWait<C> {
+    type Output = Result<V<C>, CoreFailure<V<C>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut event = as_primitive::<Event, C>(&self.handle, EVENT).expect("checked on creation").borrow_mut();
+
+        if event.set {
+            Poll::Ready(Ok(V::nil()))
+        } else {
+            add_waker(&mut event.waiters, cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use super::*;
+    use crate::{DefaultConfig, DefaultV};
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn poll(f: &mut CoreFuture<DefaultV>) -> Poll<DefaultV> {
+        let waker = Waker::from(Arc::new(Noop));
+        match Pin::new(f).poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(result) => Poll::Ready(result.unwrap()),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    #[test]
+    fn unlock_unlocked_mutex() {
+        let mutex: DefaultV = mutex_new().unwrap();
+        match mutex_unlock(&mutex) {
+            Err(CoreFailure::NotLocked { value, .. }) => assert_eq!(value, mutex),
+            _ => panic!("expected a not locked failure"),
+        }
+
+        let mut lock = mutex_lock(&mutex).unwrap();
+        assert_eq!(poll(&mut lock), Poll::Ready(V::nil()));
+        assert!(mutex_unlock(&mutex).is_ok());
+        assert!(mutex_unlock(&mutex).is_err());
+    }
+
+    #[test]
+    fn cancelled_acquire_leaves_the_queue() {
+        let mutex: DefaultV = mutex_new().unwrap();
+        let mut first = mutex_lock(&mutex).unwrap();
+        let mut second = mutex_lock(&mutex).unwrap();
+        let mut third = mutex_lock(&mutex).unwrap();
+        assert_eq!(poll(&mut first), Poll::Ready(V::nil()));
+        assert_eq!(poll(&mut second), Poll::Pending);
+        assert_eq!(poll(&mut third), Poll::Pending);
+
+        // Cancelled after being woken for the permit, which must pass on to the next waiter.
+        mutex_unlock(&mutex).unwrap();
+        drop(second);
+        assert_eq!(poll(&mut third), Poll::Ready(V::nil()));
+
+        let mut fourth = mutex_lock(&mutex).unwrap();
+        assert_eq!(poll(&mut fourth), Poll::Pending);
+        drop(fourth);
+        mutex_unlock(&mutex).unwrap();
+        let mut fifth = mutex_lock(&mutex).unwrap();
+        assert_eq!(poll(&mut fifth), Poll::Ready(V::nil()));
+    }
+
+    #[test]
+    fn cancelled_barrier_wait_does_not_count() {
+        let barrier: DefaultV = barrier_new(&V::int(2)).unwrap();
+        let mut cancelled = barrier_wait(&barrier).unwrap();
+        assert_eq!(poll(&mut cancelled), Poll::Pending);
+        drop(cancelled);
+
+        let mut first = barrier_wait(&barrier).unwrap();
+        let mut second = barrier_wait(&barrier).unwrap();
+        assert_eq!(poll(&mut first), Poll::Pending);
+        assert_eq!(poll(&mut second), Poll::Ready(V::boo(true)));
+        assert_eq!(poll(&mut first), Poll::Ready(V::boo(false)));
+    }
+
+    #[test]
+    fn repeated_polls_register_one_waker() {
+        let event: DefaultV = event_new().unwrap();
+        let mut wait = event_wait(&event).unwrap();
+        for _ in 0..3 {
+            assert_eq!(poll(&mut wait), Poll::Pending);
+        }
+        assert_eq!(as_primitive::<Event, DefaultConfig>(&event, EVENT).unwrap().borrow().waiters.len(), 1);
+
+        let barrier: DefaultV = barrier_new(&V::int(2)).unwrap();
+        let mut wait = barrier_wait(&barrier).unwrap();
+        for _ in 0..3 {
+            assert_eq!(poll(&mut wait), Poll::Pending);
+        }
+        assert_eq!(as_primitive::<Barrier, DefaultConfig>(&barrier, BARRIER).unwrap().borrow().waiters.len(), 1);
+    }
+}
@@ -0,0 +1,184 @@
+use core::cell::Cell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use super::util::*;
+use super::CoreFailure;
+use crate::{V, VvvmConfig};
+use crate::executor::{self, Join, TASK, outcome_to_value};
+use crate::future::CoreFuture;
+use crate::signature::Type;
+
+fn as_join<C: VvvmConfig>(v: &V<C>) -> Result<Join<C>, CoreFailure<V<C>>> {
+    Join::new(v.clone()).ok_or_else(|| CoreFailure::wrong_type(Type::Opaque(TASK), v.clone()))
+}
+
+pub fn spawn<C: VvvmConfig>(f: &V<C>) -> F<C> {
+    Ok(CoreFuture::ready(executor::spawn_child(f.clone(), Vec::new())))
+}
+
+pub fn join<C: VvvmConfig>(handle: &V<C>) -> F<C> {
+    let join = at_arg(0, as_join(handle))?;
+    Ok(CoreFuture::new(async move { Ok(outcome_to_value(join.await)) }))
+}
+
+pub fn cancel<C: VvvmConfig>(handle: &V<C>) -> F<C> {
+    at_arg(0, as_join(handle))?.cancel();
+    Ok(CoreFuture::ready(Ok(V::nil())))
+}
+
+pub fn join_all<C: VvvmConfig>(handles: &V<C>) -> F<C> {
+    let joins = at_arg(0, as_array(handles))?
+        .iter()
+        .map(|handle| at_arg(0, as_join(handle)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(CoreFuture::new(async move {
+        let mut outcomes = Vec::with_capacity(joins.len());
+        for join in joins {
+            outcomes.push(outcome_to_value(join.await));
+        }
+        Ok(V::Array(outcomes.into_iter().collect()))
+    }))
+}
+
+thread_local! {
+    // Where `select` starts looking for finished tasks, rotated so that no position is favoured
+    // when several tasks finish at once.
+    static NEXT_START: Cell<usize> = Cell::new(0);
+}
+
+pub fn select<C: VvvmConfig>(fs: &V<C>) -> F<C> {
+    let items = at_arg(0, as_array(fs))?;
+    if items.is_empty() {
+        return Err(CoreFailure::wrong_type(Type::NonEmptyArray(&Type::Any), fs.clone()).at_arg(0));
+    }
+
+    // Whether each task was spawned here rather than passed in as a handle.
+    let mut joins: Vec<(Join<C>, bool)> = Vec::with_capacity(items.len());
+    for item in items.iter() {
+        if let Some(join) = Join::new(item.clone()) {
+            joins.push((join, false));
+            continue;
+        }
+
+        match executor::spawn_child(item.clone(), Vec::new()) {
+            Ok(handle) => joins.push((Join::new(handle).expect("a freshly spawned task handle"), true)),
+            Err(e) => {
+                // Nobody could observe or cancel the tasks spawned so far.
+                for (join, spawned) in joins.iter() {
+                    if *spawned {
+                        join.cancel();
+                    }
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    let start = NEXT_START.with(|next| {
+        let start = next.get();
+        next.set(start.wrapping_add(1));
+        start
+    });
+    Ok(CoreFuture::new(Select {
+        joins: joins.into_iter().map(|(join, _)| join).collect(),
+        start,
+    }))
+}
+
+// Resolves to `[index, outcome]` for the first of the tasks to finish, and cancels the others.
+struct Select<C: VvvmConfig> {
+    joins: Vec<Join<C>>,
+    start: usize,
+}
+
+impl<C: VvvmConfig> Future for Select<C> {
+    type Output = Result<V<C>, CoreFailure<V<C>>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let count = self.joins.len();
+        for i in 0..count {
+            let index = self.start.wrapping_add(i) % count;
+            if let Poll::Ready(outcome) = Pin::new(&mut self.joins[index]).poll(cx) {
+                for join in self.joins.iter() {
+                    join.cancel();
+                }
+                return Poll::Ready(Ok(V::Array(vec![V::int(index as i64), outcome_to_value(outcome)].into_iter().collect())));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::select;
+    use crate::{V, DefaultConfig, DefaultV, VvvmResult, CoreFailure};
+    use crate::executor::{Executor, Join, Task, spawn_child, outcome_to_value};
+    use crate::future::CoreFuture;
+    use crate::signature::Type;
+
+    fn array(items: Vec<DefaultV>) -> DefaultV {
+        V::Array(items.into_iter().collect())
+    }
+
+    // Tasks of an int `n` yield to other tasks `n` times, then finish with `n`.
+    fn run(f: DefaultV, _args: Vec<DefaultV>) -> Task<DefaultConfig> {
+        Box::pin(async move {
+            let n = match f {
+                V::Int(n) => n,
+                _ => panic!("tasks are spawned for ints"),
+            };
+            for _ in 0..n {
+                CoreFuture::<DefaultV>::yield_then(V::nil()).await?;
+            }
+            Ok(V::int(n))
+        })
+    }
+
+    fn run_host<F>(f: F) -> (Executor<DefaultConfig>, VvvmResult<DefaultConfig>) where
+        F: core::future::Future<Output = VvvmResult<DefaultConfig>> + 'static,
+    {
+        let mut executor = Executor::new();
+        executor.set_runner(run);
+        let id = executor.spawn(f);
+        let result = executor.run().remove(&id).expect("a finished host task");
+        (executor, result)
+    }
+
+    #[test]
+    fn select_empty() {
+        match select::<DefaultConfig>(&array(vec![])) {
+            Err(CoreFailure::WrongType { expected: Type::NonEmptyArray(_), .. }) => {}
+            _ => panic!("expected a wrong type failure"),
+        }
+    }
+
+    #[test]
+    fn select_first_to_finish() {
+        let (executor, result) = run_host(async {
+            Ok(select(&array(vec![V::int(3), V::int(1), V::int(2)]))?.await?)
+        });
+        assert_eq!(result.ok(), Some(array(vec![V::int(1), V::ok(V::int(1))])));
+        assert_eq!(executor.pending(), 0);
+        assert!(executor.is_empty());
+    }
+
+    #[test]
+    fn select_task_handles() {
+        let (_, result) = run_host(async {
+            let slow = spawn_child(V::int(10), Vec::new())?;
+            let selected = select(&array(vec![slow.clone(), V::int(0)]))?.await?;
+            let slow = outcome_to_value(Join::new(slow).unwrap().await);
+            Ok(array(vec![selected, slow]))
+        });
+
+        let cancelled = CoreFailure::<DefaultV>::cancelled().to_value();
+        assert_eq!(result.ok(), Some(array(vec![
+            array(vec![V::int(1), V::ok(V::int(0))]),
+            V::err(cancelled),
+        ])));
+    }
+}
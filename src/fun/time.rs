@@ -0,0 +1,188 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::rc::Rc;
+
+use super::util::*;
+use super::CoreFailure;
+use crate::{V, VvvmConfig};
+use crate::clock::{Clock, TimerId};
+use crate::executor::{self, Join, outcome_to_value};
+use crate::future::CoreFuture;
+
+fun!(now() {
+    Ok(V::int(executor::clock::<C>()?.now() as i64))
+});
+
+pub fn sleep<C: VvvmConfig>(ms: &V<C>) -> F<C> {
+    let sleep = Sleep::new(executor::clock::<C>()?, at_arg(0, as_positive_int(ms))?);
+    Ok(CoreFuture::new(async move {
+        sleep.await;
+        Ok(V::nil())
+    }))
+}
+
+pub fn timeout<C: VvvmConfig>(ms: &V<C>, f: &V<C>) -> F<C> {
+    let sleep = Sleep::new(executor::clock::<C>()?, at_arg(0, as_positive_int(ms))?);
+    let handle = executor::spawn_child(f.clone(), Vec::new())?;
+    Ok(CoreFuture::new(Timeout {
+        join: Join::new(handle).expect("a freshly spawned task handle"),
+        sleep,
+    }))
+}
+
+// Completes once the clock reaches the deadline.
+struct Sleep {
+    clock: Rc<dyn Clock>,
+    deadline: u64,
+    // The registered timer and the waker it wakes, so that repeated polls don't add timers.
+    timer: Option<(TimerId, Waker)>,
+}
+
+impl Sleep {
+    fn new(clock: Rc<dyn Clock>, ms: i64) -> Self {
+        let deadline = clock.now().saturating_add(ms as u64);
+        Sleep { clock, deadline, timer: None }
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.clock.now() >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        match &self.timer {
+            Some((_, waker)) if waker.will_wake(cx.waker()) => {}
+            _ => {
+                if let Some((timer, _)) = self.timer.take() {
+                    self.clock.cancel(timer);
+                }
+                let timer = self.clock.wake_at(self.deadline, cx.waker().clone());
+                self.timer = Some((timer, cx.waker().clone()));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some((timer, _)) = self.timer.take() {
+            self.clock.cancel(timer);
+        }
+    }
+}
+
+// Resolves to the outcome of the task, or cancels it and resolves to `err("timeout")` if the
+// deadline passes first.
+struct Timeout<C: VvvmConfig> {
+    join: Join<C>,
+    sleep: Sleep,
+}
+
+impl<C: VvvmConfig> Future for Timeout<C> {
+    type Output = Result<V<C>, CoreFailure<V<C>>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        if let Poll::Ready(outcome) = Pin::new(&mut self.join).poll(cx) {
+            return Poll::Ready(Ok(outcome_to_value(outcome)));
+        }
+
+        match Pin::new(&mut self.sleep).poll(cx) {
+            Poll::Ready(()) => {
+                self.join.cancel();
+                Poll::Ready(Ok(V::err(V::string("timeout"))))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use super::{Sleep, now, sleep, timeout};
+    use crate::{V, DefaultConfig, DefaultV, VvvmResult};
+    use crate::clock::{Clock, VirtualClock};
+    use crate::executor::{Executor, Task};
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    #[test]
+    fn sleep_registers_one_timer_and_removes_it_when_dropped() {
+        let clock = Rc::new(VirtualClock::new());
+        let waker = Waker::from(Arc::new(Noop));
+        let mut cx = Context::from_waker(&waker);
+
+        let mut sleep = Sleep::new(clock.clone(), 10);
+        for _ in 0..3 {
+            assert_eq!(Pin::new(&mut sleep).poll(&mut cx), Poll::Pending);
+        }
+        drop(sleep);
+        assert!(!clock.advance());
+
+        let mut sleep = Sleep::new(clock.clone(), 10);
+        assert_eq!(Pin::new(&mut sleep).poll(&mut cx), Poll::Pending);
+        assert!(clock.advance());
+        assert_eq!(clock.now(), 10);
+        assert_eq!(Pin::new(&mut sleep).poll(&mut cx), Poll::Ready(()));
+        assert!(!clock.advance());
+    }
+
+    // Tasks of an int `n` sleep for `n` milliseconds, then finish with `n`.
+    fn run(f: DefaultV, _args: Vec<DefaultV>) -> Task<DefaultConfig> {
+        Box::pin(async move {
+            sleep(&f)?.await?;
+            Ok(f)
+        })
+    }
+
+    fn run_host<F>(f: F) -> (Executor<DefaultConfig>, VvvmResult<DefaultConfig>) where
+        F: Future<Output = VvvmResult<DefaultConfig>> + 'static,
+    {
+        let mut executor = Executor::new();
+        executor.set_runner(run);
+        executor.set_clock(Rc::new(VirtualClock::new()));
+        let id = executor.spawn(f);
+        let result = executor.run().remove(&id).expect("a finished host task");
+        (executor, result)
+    }
+
+    fn array(items: Vec<DefaultV>) -> DefaultV {
+        V::Array(items.into_iter().collect())
+    }
+
+    #[test]
+    fn timeout_finishing_in_time() {
+        let (executor, result) = run_host(async {
+            let outcome = timeout(&V::int(10), &V::int(5))?.await?;
+            Ok(array(vec![outcome, now()?]))
+        });
+        assert_eq!(result.ok(), Some(array(vec![V::ok(V::int(5)), V::int(5)])));
+        assert_eq!(executor.pending(), 0);
+    }
+
+    #[test]
+    fn timeout_expiring() {
+        let (executor, result) = run_host(async {
+            let outcome = timeout(&V::int(10), &V::int(20))?.await?;
+            Ok(array(vec![outcome, now()?]))
+        });
+        assert_eq!(result.ok(), Some(array(vec![V::err(V::string("timeout")), V::int(10)])));
+        assert_eq!(executor.pending(), 0);
+        assert!(executor.is_empty());
+    }
+}
@@ -1,95 +1,124 @@
 #![macro_use]
 
-use crate::{V, ValueBaseOrdered, ValueBase, VvvmFailure, VvvmFuture};
+use core::task::Waker;
+
+use gc_immutable_collections::Array;
+
+use crate::{V, VvvmConfig, VvvmFailure, VvvmFuture};
 use super::CoreFailure;
+use crate::future::CoreFuture;
+use crate::signature::Type;
 
-pub type R<SS, SA, DS, DA, F, Fut> = Result<V<SS, SA, DS, DA, F, Fut>, CoreFailure<V<SS, SA, DS, DA, F, Fut>>>;
+pub type R<C> = Result<V<C>, CoreFailure<V<C>>>;
 
+// The asynchronous functions validate their arguments up front, the returned future only fails
+// if the function itself fails.
+pub type F<C> = Result<CoreFuture<V<C>>, CoreFailure<V<C>>>;
+
+// Arguments can be annotated with the type they must have (`int`, `positive_int`,
+// `strictly_positive_int`, `non_zero_int`, `float`, `bool` or `array`), the body then sees the
+// converted argument, and failed conversions record the index of the argument.
 macro_rules! fun {
-    ($name:ident ($($arg:ident),*) $body:block) => (
-        pub fn $name <SS, SA, DS, DA, F, Fut>($($arg: &V<SS, SA, DS, DA, F, Fut>),*) -> R<SS, SA, DS, DA, F, Fut> where
-            SS: ValueBaseOrdered,
-            DS: ValueBase,
-            SA: ValueBaseOrdered,
-            DA: ValueBase,
-            F: 'static,
-            Fut: 'static,
-        {
+    ($name:ident ($($arg:ident $(: $ty:ident)?),*) $body:block) => (
+        #[allow(unused_assignments, unused_mut, unused_variables)]
+        pub fn $name<C: VvvmConfig>($($arg: &V<C>),*) -> R<C> {
+            let mut index = 0;
+            $(
+                let $arg = fun!(@convert index, $arg $(, $ty)?);
+                index += 1;
+            )*
             $body
         }
     );
+
+    (@convert $index:ident, $arg:ident) => ($arg);
+    (@convert $index:ident, $arg:ident, int) => (at_arg($index, as_int($arg))?);
+    (@convert $index:ident, $arg:ident, positive_int) => (at_arg($index, as_positive_int($arg))?);
+    (@convert $index:ident, $arg:ident, strictly_positive_int) => (at_arg($index, as_strictly_positive_int($arg))?);
+    (@convert $index:ident, $arg:ident, non_zero_int) => (at_arg($index, as_non_zero_int($arg))?);
+    (@convert $index:ident, $arg:ident, float) => (at_arg($index, as_float($arg))?);
+    (@convert $index:ident, $arg:ident, bool) => (at_arg($index, as_bool($arg))?);
+    (@convert $index:ident, $arg:ident, array) => (at_arg($index, as_array($arg))?);
 }
 
-pub fn as_bool<SS, SA, DS, DA, F, Fut>(v: &V<SS, SA, DS, DA, F, Fut>) -> Result<bool, CoreFailure<V<SS, SA, DS, DA, F, Fut>>> where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+/// Attribute a failure to the argument at `index`.
+pub fn at_arg<T, C: VvvmConfig>(index: usize, r: Result<T, CoreFailure<V<C>>>) -> Result<T, CoreFailure<V<C>>> {
+    r.map_err(|e| e.at_arg(index))
+}
+
+pub fn as_bool<C: VvvmConfig>(v: &V<C>) -> Result<bool, CoreFailure<V<C>>> {
     match v {
         V::Bool(b) => Ok(b.clone()),
-        _ => Err(CoreFailure::NotBool(v.clone())),
+        _ => Err(CoreFailure::wrong_type(Type::Bool, v.clone())),
     }
 }
 
-pub fn as_float<SS, SA, DS, DA, F, Fut>(v: &V<SS, SA, DS, DA, F, Fut>) -> Result<f64, CoreFailure<V<SS, SA, DS, DA, F, Fut>>> where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+pub fn as_float<C: VvvmConfig>(v: &V<C>) -> Result<f64, CoreFailure<V<C>>> {
     match v {
         V::Float(f) => Ok(f.0.clone()),
-        _ => Err(CoreFailure::NotFloat(v.clone())),
+        _ => Err(CoreFailure::wrong_type(Type::Float, v.clone())),
     }
 }
 
-pub fn as_int<SS, SA, DS, DA, F, Fut>(v: &V<SS, SA, DS, DA, F, Fut>) -> Result<i64, CoreFailure<V<SS, SA, DS, DA, F, Fut>>> where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+pub fn as_int<C: VvvmConfig>(v: &V<C>) -> Result<i64, CoreFailure<V<C>>> {
     match v {
         V::Int(n) => Ok(n.clone()),
-        _ => Err(CoreFailure::NotInt(v.clone())),
+        _ => Err(CoreFailure::wrong_type(Type::Int, v.clone())),
     }
 }
 
-pub fn as_positive_int<SS, SA, DS, DA, F, Fut>(v: &V<SS, SA, DS, DA, F, Fut>) -> Result<i64, CoreFailure<V<SS, SA, DS, DA, F, Fut>>> where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+pub fn as_positive_int<C: VvvmConfig>(v: &V<C>) -> Result<i64, CoreFailure<V<C>>> {
     let n = as_int(v)?;
     if n >= 0 {
         Ok(n)
     } else {
-        Err(CoreFailure::NotPositiveInt(v.clone()))
+        Err(CoreFailure::wrong_type(Type::PositiveInt, v.clone()))
     }
 }
 
-pub fn as_non_zero_int<SS, SA, DS, DA, F, Fut>(v: &V<SS, SA, DS, DA, F, Fut>) -> Result<i64, CoreFailure<V<SS, SA, DS, DA, F, Fut>>> where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+pub fn as_strictly_positive_int<C: VvvmConfig>(v: &V<C>) -> Result<i64, CoreFailure<V<C>>> {
+    let n = as_int(v)?;
+    if n > 0 {
+        Ok(n)
+    } else {
+        Err(CoreFailure::wrong_type(Type::StrictlyPositiveInt, v.clone()))
+    }
+}
+
+pub fn as_non_zero_int<C: VvvmConfig>(v: &V<C>) -> Result<i64, CoreFailure<V<C>>> {
     let n = as_int(v)?;
     if n != 0 {
         Ok(n)
     } else {
-        Err(CoreFailure::NotNonZeroInt(v.clone()))
+        Err(CoreFailure::wrong_type(Type::NonZeroInt, v.clone()))
+    }
+}
+
+pub fn as_array<C: VvvmConfig>(v: &V<C>) -> Result<&Array<V<C>>, CoreFailure<V<C>>> {
+    match v {
+        V::Array(a) => Ok(a),
+        _ => Err(CoreFailure::wrong_type(Type::Array(&Type::Any), v.clone())),
+    }
+}
+
+/// Unpack an optional parameter, `nil` means it was left out.
+pub fn optional<C: VvvmConfig>(v: &V<C>) -> Option<&V<C>> {
+    match v {
+        V::Nil => None,
+        _ => Some(v),
+    }
+}
+
+/// Remember `waker` unless it would wake the same task as one of `wakers`, so that a future that
+/// is polled repeatedly while waiting doesn't pile up wakers.
+pub fn add_waker(wakers: &mut Vec<Waker>, waker: &Waker) {
+    if !wakers.iter().any(|w| w.will_wake(waker)) {
+        wakers.push(waker.clone());
+    }
+}
+
+pub fn wake_all(wakers: &mut Vec<Waker>) {
+    for waker in wakers.drain(..) {
+        waker.wake();
     }
 }
@@ -2,16 +2,23 @@ use guvm_rs::Value;
 
 use super::util::*;
 use super::CoreFailure;
-use crate::{V, ValueBaseOrdered, ValueBase};
+use crate::{V, VvvmConfig};
+use crate::pretty::PrettyOptions;
 
 fun!(halt(v) {
-    Err(CoreFailure::Halt(v.clone()))
+    Err(CoreFailure::halt(v.clone()))
 });
 
 fun!(type_of(v) {
     match v {
         V::Nil => Ok(V::string("nil")),
-        _ => Ok(V::string("foo")),
+        V::Bool(_) => Ok(V::string("bool")),
+        V::Int(_) => Ok(V::string("int")),
+        V::Float(_) => Ok(V::string("float")),
+        V::Array(_) => Ok(V::string("array")),
+        V::Map(_) => Ok(V::string("map")),
+        V::Fun(_) => Ok(V::string("function")),
+        V::Opaque(o) => Ok(V::string(o.kind())),
     }
 });
 
@@ -22,3 +29,7 @@ fun!(truthy(v) {
 fun!(falsey(v) {
     Ok(V::boo(!v.truthy()))
 });
+
+fun!(pretty(v) {
+    Ok(V::string(&crate::pretty::pretty(v, &PrettyOptions::default())))
+});
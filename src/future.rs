@@ -0,0 +1,252 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::{V, VvvmConfig, VvvmFailure, VvvmFuture, CoreFailure};
+
+/// What a `VvvmFuture` resolves to.
+pub type VvvmResult<C> = Result<V<C>, VvvmFailure<C>>;
+
+/// The future returned by the asynchronous core functions.
+pub struct CoreFuture<Val>(CoreFutureState<Val>);
+
+enum CoreFutureState<Val> {
+    Ready(Option<Result<Val, CoreFailure<Val>>>),
+    // Returns `Pending` on the first poll, after waking the task so that it gets polled again.
+    Yield {
+        yielded: bool,
+        value: Option<Val>,
+    },
+    Boxed(Pin<Box<dyn Future<Output = Result<Val, CoreFailure<Val>>>>>),
+}
+
+impl<Val> CoreFuture<Val> {
+    /// A future that completes immediately.
+    pub fn ready(result: Result<Val, CoreFailure<Val>>) -> Self {
+        CoreFuture(CoreFutureState::Ready(Some(result)))
+    }
+
+    /// A future that gives other tasks the chance to run before completing with `value`: the
+    /// first poll wakes the task and returns `Pending`, the second one returns the value.
+    pub fn yield_then(value: Val) -> Self {
+        CoreFuture(CoreFutureState::Yield {
+            yielded: false,
+            value: Some(value),
+        })
+    }
+}
+
+impl<Val: 'static> CoreFuture<Val> {
+    /// Wrap an arbitrary future, for core functions that need to wait for other tasks.
+    pub fn new<F>(f: F) -> Self where
+        F: Future<Output = Result<Val, CoreFailure<Val>>> + 'static,
+    {
+        CoreFuture(CoreFutureState::Boxed(Box::pin(f)))
+    }
+}
+
+// None of the states are self-referential.
+impl<Val> Unpin for CoreFuture<Val> {}
+
+impl<Val> Future for CoreFuture<Val> {
+    type Output = Result<Val, CoreFailure<Val>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match &mut self.get_mut().0 {
+            CoreFutureState::Ready(result) => {
+                Poll::Ready(result.take().expect("CoreFuture polled after completion"))
+            }
+            CoreFutureState::Yield { yielded, value } => {
+                if *yielded {
+                    Poll::Ready(Ok(value.take().expect("CoreFuture polled after completion")))
+                } else {
+                    *yielded = true;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            }
+            CoreFutureState::Boxed(f) => f.as_mut().poll(cx),
+        }
+    }
+}
+
+/// A pinned reference to the future inside a `VvvmFuture`, see `VvvmFuture::project`.
+pub enum VvvmFutureProjection<'a, C: VvvmConfig> {
+    Core(Pin<&'a mut CoreFuture<V<C>>>),
+    Other(Pin<&'a mut C::Future>),
+    Boxed(Pin<&'a mut dyn Future<Output = VvvmResult<C>>>),
+}
+
+impl<C: VvvmConfig> VvvmFuture<C> {
+    /// Pin projection onto the wrapped future.
+    pub fn project(self: Pin<&mut Self>) -> VvvmFutureProjection<'_, C> {
+        // Safety: the wrapped futures are structurally pinned. `VvvmFuture` does not implement
+        // `Drop`, is only `Unpin` if the wrapped futures are, and never moves them out of a pinned
+        // reference.
+        unsafe {
+            match self.get_unchecked_mut() {
+                VvvmFuture::Core(f) => VvvmFutureProjection::Core(Pin::new_unchecked(f)),
+                VvvmFuture::Other(f) => VvvmFutureProjection::Other(Pin::new_unchecked(f)),
+                VvvmFuture::Boxed(f) => VvvmFutureProjection::Boxed(f.as_mut()),
+            }
+        }
+    }
+}
+
+impl<C: VvvmConfig> Future for VvvmFuture<C> where
+    C::Future: Future<Output = VvvmResult<C>>,
+{
+    type Output = VvvmResult<C>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match self.project() {
+            VvvmFutureProjection::Core(f) => f.poll(cx).map(|r| r.map_err(VvvmFailure::Core)),
+            VvvmFutureProjection::Other(f) => f.poll(cx),
+            VvvmFutureProjection::Boxed(f) => f.poll(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Wake;
+    use core::pin::Pin;
+    use core::ptr;
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    use crate::{V, VvvmFailure, VvvmFuture, CoreFailure, DefaultConfig, DefaultV, GenericConfig, Never};
+    use super::{VvvmResult, CoreFuture};
+
+    fn noop_raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            noop_raw_waker()
+        }
+        fn noop(_: *const ()) {}
+
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+
+    fn noop_waker() -> Waker {
+        unsafe { Waker::from_raw(noop_raw_waker()) }
+    }
+
+    // Pending on the first poll, then ready with the number of times it was polled.
+    struct TwoPolls(i64);
+
+    type TestConfig = GenericConfig<Never, Never, Never, Never, (), TwoPolls>;
+
+    impl Future for TwoPolls {
+        type Output = VvvmResult<TestConfig>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            self.0 += 1;
+            if self.0 < 2 {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            } else {
+                Poll::Ready(Ok(V::int(self.0)))
+            }
+        }
+    }
+
+    #[test]
+    fn core_value() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut fut: VvvmFuture<DefaultConfig> = VvvmFuture::Core(CoreFuture::ready(Ok(V::int(42))));
+
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(Ok(V::Int(42))) => {}
+            _ => panic!("expected the core value"),
+        }
+    }
+
+    #[test]
+    fn core_failure() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut fut: VvvmFuture<DefaultConfig> = VvvmFuture::Core(CoreFuture::ready(Err(CoreFailure::overflow(vec![1, 2]))));
+
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(Err(VvvmFailure::Core(CoreFailure::Overflow { operands, .. }))) => {
+                assert_eq!(operands, vec![1, 2]);
+            }
+            _ => panic!("expected the core failure"),
+        }
+    }
+
+    #[test]
+    fn other_future() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut fut: VvvmFuture<TestConfig> = VvvmFuture::Other(TwoPolls(0));
+
+        assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(Ok(V::Int(2))) => {}
+            _ => panic!("expected the result of the second poll"),
+        }
+    }
+
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn yield_wakes_and_completes_on_second_poll() {
+        let flag = Arc::new(Flag(AtomicBool::new(false)));
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut fut: CoreFuture<DefaultV> = CoreFuture::yield_then(V::int(7));
+
+        assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+        assert!(flag.0.load(Ordering::SeqCst));
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(Ok(V::Int(7))) => {}
+            _ => panic!("expected the value after yielding"),
+        }
+    }
+
+    #[test]
+    fn yielding_tasks_alternate() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let task = |name: &'static str| {
+            let log = log.clone();
+            async move {
+                for i in 0..3 {
+                    log.borrow_mut().push((name, i));
+                    let _ = CoreFuture::<DefaultV>::yield_then(V::nil()).await;
+                }
+            }
+        };
+
+        // A minimal round-robin scheduler.
+        let mut tasks: VecDeque<Pin<Box<dyn Future<Output = ()>>>> = VecDeque::new();
+        tasks.push_back(Box::pin(task("a")));
+        tasks.push_back(Box::pin(task("b")));
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        while let Some(mut task) = tasks.pop_front() {
+            if task.as_mut().poll(&mut cx).is_pending() {
+                tasks.push_back(task);
+            }
+        }
+
+        assert_eq!(
+            *log.borrow(),
+            vec![("a", 0), ("b", 0), ("a", 1), ("b", 1), ("a", 2), ("b", 2)],
+        );
+    }
+}
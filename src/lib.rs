@@ -1,4 +1,7 @@
 use core::cmp::Ordering;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
 
 use gc::{Gc, GcCell, Trace, Finalize, custom_trace};
 use gc_derive::{Trace, Finalize};
@@ -9,35 +12,77 @@ use gc_immutable_collections::{Array, Map};
 mod float;
 use float::PavoFloat;
 
+mod failure;
+pub use failure::{CoreFailure, Location};
+
+mod future;
+pub use future::{CoreFuture, VvvmResult, VvvmFutureProjection};
+
+pub mod executor;
+
+pub mod encoding;
+
+pub mod record;
+pub use record::{Recorder, Recording};
+
+pub mod fuel;
+pub use fuel::Fuel;
+
+pub mod clock;
+
 mod fun;
-use fun::{Fun, SynchronousFun, AsynchronousFun, CoreFailure, CoreFuture};
+pub use fun::{
+    Fun,
+    SynchronousFun,
+    AsynchronousFun,
+    DynamicSynchronous,
+    SynchronousCoreFunction,
+    AsynchronousCoreFunction,
+    DynamicCoreFunction,
+    Parameters,
+};
+
+pub mod pretty;
+
+#[doc(hidden)]
+pub mod macros;
+
+pub mod convert;
+pub use convert::{FromValue, IntoValue};
+pub use vvvm_derive::{FromValue, IntoValue};
+
+pub mod native;
+
+pub mod registry;
+pub use registry::CoreFunction;
+
+pub mod signature;
+
+pub mod prelude;
+pub use prelude::{Prelude, prelude};
+
+pub mod sandbox;
+pub use sandbox::Sandbox;
+
+pub mod opaque;
+pub use opaque::Opaque;
+
+mod never;
+pub use never::{Never, NeverFuture, DefaultConfig, DefaultV};
 
 #[derive(Finalize)]
-pub enum V<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+pub enum V<C: VvvmConfig> {
     Nil,
     Bool(bool),
     Float(PavoFloat),
     Int(i64),
     Array(Array<Self>),
     Map(Map<Self, Self>),
-    Fun(Fun<SS, SA, DS, DA, F, Fut>),
+    Fun(Fun<C>),
+    Opaque(Opaque),
 }
 
-impl<SS, SA, DS, DA, F, Fut> Clone for V<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-{
+impl<C: VvvmConfig> Clone for V<C> {
     fn clone(&self) -> Self {
         match self {
             V::Nil => V::Nil,
@@ -47,22 +92,15 @@ where
             V::Array(a) => V::Array(a.clone()),
             V::Map(m) => V::Map(m.clone()),
             V::Fun(f) => V::Fun(f.clone()),
+            V::Opaque(o) => V::Opaque(o.clone()),
         }
     }
 }
 
-unsafe impl<SS, SA, DS, DA, F, Fut> Trace for V<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+unsafe impl<C: VvvmConfig> Trace for V<C> {
     custom_trace!(this, {
         match this {
-            V::Nil | V::Bool(_) | V::Float(_) | V::Int(_) => {}
+            V::Nil | V::Bool(_) | V::Float(_) | V::Int(_) | V::Opaque(_) => {}
             V::Array(a) => mark(a),
             V::Map(m) => mark(m),
             V::Fun(f) => mark(f),
@@ -70,85 +108,49 @@ where
     });
 }
 
-impl<SS, SA, DS, DA, F, Fut> Default for V<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> Default for V<C> {
     fn default() -> Self {
         V::Nil
     }
 }
 
-impl<SS, SA, DS, DA, F, Fut> PartialEq for V<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> PartialEq for V<C> {
     fn eq(&self, other: &Self) -> bool {
-        unimplemented!()
+        self.cmp(other) == Ordering::Equal
     }
 }
 
-impl<SS, SA, DS, DA, F, Fut> Eq for V<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{}
+impl<C: VvvmConfig> Eq for V<C> {}
 
-impl<SS, SA, DS, DA, F, Fut> PartialOrd for V<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> PartialOrd for V<C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        unimplemented!()
+        Some(self.cmp(other))
     }
 }
 
-impl<SS, SA, DS, DA, F, Fut> Ord for V<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+/// The total order on values: values of different types are ordered `nil < bools < ints < floats
+/// < arrays < maps < functions < opaque values`, arrays and maps compare lexicographically.
+impl<C: VvvmConfig> Ord for V<C> {
     fn cmp(&self, other: &Self) -> Ordering {
-        unimplemented!()
+        match (self, other) {
+            (V::Nil, V::Nil) => Ordering::Equal,
+            (V::Bool(a), V::Bool(b)) => a.cmp(b),
+            (V::Int(a), V::Int(b)) => a.cmp(b),
+            (V::Float(a), V::Float(b)) => a.cmp(b),
+            (V::Array(a), V::Array(b)) => a.iter().cmp(b.iter()),
+            (V::Map(a), V::Map(b)) => a.iter().cmp(b.iter()),
+            (V::Fun(a), V::Fun(b)) => a.cmp(b),
+            (V::Opaque(a), V::Opaque(b)) => a.cmp(b),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
     }
 }
 
-impl<SS, SA, DS, DA, F, Fut> Value for V<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
-    type Failure = VvvmFailure<Self, F>;
-    type Fut = VvvmFuture<Self, Fut>;
-    type BuiltInFunction = SynchronousFun<SS, DS>;
-    type BuiltInAsync = AsynchronousFun<SA, DA>;
+impl<C: VvvmConfig> Value for V<C> {
+    type Failure = VvvmFailure<C>;
+    type Fut = VvvmFuture<C>;
+    type BuiltInFunction = SynchronousFun<C>;
+    type BuiltInAsync = AsynchronousFun<C>;
 
     fn truthy(&self) -> bool {
         match self {
@@ -218,15 +220,7 @@ where
     }
 }
 
-impl<SS, SA, DS, DA, F, Fut> V<SS, SA, DS, DA, F, Fut>
-where
-    SS: ValueBaseOrdered,
-    DS: ValueBase,
-    SA: ValueBaseOrdered,
-    DA: ValueBase,
-    F: 'static,
-    Fut: 'static,
-{
+impl<C: VvvmConfig> V<C> {
     pub fn nil() -> Self {
         V::Nil
     }
@@ -243,83 +237,276 @@ where
         V::Int(n)
     }
 
+    /// A string, represented as the array of its utf-8 bytes.
     pub fn string(s: &str) -> Self {
-        unimplemented!()
+        V::Array(s.bytes().map(|b| V::Int(b as i64)).collect())
     }
 
+    /// A successful result, represented as the map `{"ok": v}`.
     pub fn ok(v: Self) -> Self {
-        unimplemented!()
+        V::Map(vec![(V::string("ok"), v)].into_iter().collect())
     }
 
+    /// A failed result, represented as the map `{"err": v}`.
     pub fn err(v: Self) -> Self {
-        unimplemented!()
+        V::Map(vec![(V::string("err"), v)].into_iter().collect())
     }
 
     pub fn err_nil() -> Self {
         Self::err(Self::nil())
     }
 
+    /// The partial order on values: only values of the same type are comparable, floats compare
+    /// as in IEEE 754 (so `NaN` is incomparable), arrays and maps compare lexicographically by the
+    /// partial order on their items, and distinct functions or opaque values are incomparable.
     pub fn partial_compare(&self, other: &Self) -> Option<Ordering> {
-        unimplemented!()
+        match (self, other) {
+            (V::Nil, V::Nil) => Some(Ordering::Equal),
+            (V::Bool(a), V::Bool(b)) => Some(a.cmp(b)),
+            (V::Int(a), V::Int(b)) => Some(a.cmp(b)),
+            (V::Float(a), V::Float(b)) => a.0.partial_cmp(&b.0),
+            (V::Array(a), V::Array(b)) => {
+                let mut a = a.iter();
+                let mut b = b.iter();
+                loop {
+                    match (a.next(), b.next()) {
+                        (None, None) => return Some(Ordering::Equal),
+                        (None, Some(_)) => return Some(Ordering::Less),
+                        (Some(_), None) => return Some(Ordering::Greater),
+                        (Some(x), Some(y)) => match x.partial_compare(y)? {
+                            Ordering::Equal => {}
+                            ordering => return Some(ordering),
+                        },
+                    }
+                }
+            }
+            (V::Map(a), V::Map(b)) => {
+                let mut a = a.iter();
+                let mut b = b.iter();
+                loop {
+                    match (a.next(), b.next()) {
+                        (None, None) => return Some(Ordering::Equal),
+                        (None, Some(_)) => return Some(Ordering::Less),
+                        (Some(_), None) => return Some(Ordering::Greater),
+                        (Some((kx, vx)), Some((ky, vy))) => match kx.partial_compare(ky)? {
+                            Ordering::Equal => match vx.partial_compare(vy)? {
+                                Ordering::Equal => {}
+                                ordering => return Some(ordering),
+                            },
+                            ordering => return Some(ordering),
+                        },
+                    }
+                }
+            }
+            (V::Fun(a), V::Fun(b)) if a == b => Some(Ordering::Equal),
+            (V::Opaque(a), V::Opaque(b)) if a == b => Some(Ordering::Equal),
+            _ => None,
+        }
     }
 
     pub fn partial_lt(&self, other: &Self) -> Option<bool> {
-        unimplemented!()
+        self.partial_compare(other).map(|o| o == Ordering::Less)
     }
 
     pub fn partial_leq(&self, other: &Self) -> Option<bool> {
-        unimplemented!()
+        self.partial_compare(other).map(|o| o != Ordering::Greater)
     }
 
     pub fn partial_eq(&self, other: &Self) -> Option<bool> {
-        unimplemented!()
+        self.partial_compare(other).map(|o| o == Ordering::Equal)
     }
 
     pub fn partial_geq(&self, other: &Self) -> Option<bool> {
-        unimplemented!()
+        self.partial_compare(other).map(|o| o != Ordering::Less)
     }
 
     pub fn partial_gt(&self, other: &Self) -> Option<bool> {
-        unimplemented!()
+        self.partial_compare(other).map(|o| o == Ordering::Greater)
     }
 
     pub fn partial_neq(&self, other: &Self) -> Option<bool> {
-        unimplemented!()
+        self.partial_compare(other).map(|o| o != Ordering::Equal)
     }
 
+    /// The lesser of the two values, `None` if they are incomparable.
     pub fn partial_greatest_lower_bound(&self, other: &Self) -> Option<Self> {
-        unimplemented!()
+        match self.partial_compare(other)? {
+            Ordering::Greater => Some(other.clone()),
+            _ => Some(self.clone()),
+        }
     }
 
+    /// The greater of the two values, `None` if they are incomparable.
     pub fn partial_least_upper_bound(&self, other: &Self) -> Option<Self> {
-        unimplemented!()
+        match self.partial_compare(other)? {
+            Ordering::Less => Some(other.clone()),
+            _ => Some(self.clone()),
+        }
+    }
+
+    // The position of the type of the value in the total order.
+    fn type_rank(&self) -> u8 {
+        match self {
+            V::Nil => 0,
+            V::Bool(_) => 1,
+            V::Int(_) => 2,
+            V::Float(_) => 3,
+            V::Array(_) => 4,
+            V::Map(_) => 5,
+            V::Fun(_) => 6,
+            V::Opaque(_) => 7,
+        }
+    }
+}
+
+impl<C: VvvmConfig> From<bool> for V<C> {
+    fn from(b: bool) -> Self {
+        V::boo(b)
+    }
+}
+
+impl<C: VvvmConfig> From<i64> for V<C> {
+    fn from(n: i64) -> Self {
+        V::int(n)
+    }
+}
+
+impl<C: VvvmConfig> From<f64> for V<C> {
+    fn from(x: f64) -> Self {
+        V::float(x)
     }
 }
 
-pub enum VvvmFailure<Val, F> {
-    Core(CoreFailure<Val>),
-    Other(F),
+pub enum VvvmFailure<C: VvvmConfig> {
+    Core(CoreFailure<V<C>>),
+    Other(C::Failure),
 }
 
-impl<Val, F> From<CoreFailure<Val>> for VvvmFailure<Val, F> {
-    fn from(f: CoreFailure<Val>) -> Self {
+impl<C: VvvmConfig> From<CoreFailure<V<C>>> for VvvmFailure<C> {
+    fn from(f: CoreFailure<V<C>>) -> Self {
         VvvmFailure::Core(f)
     }
 }
 
-pub enum VvvmFuture<Val, Fut> {
-    Core(CoreFuture<Val>),
-    Other(Fut),
+pub enum VvvmFuture<C: VvvmConfig> {
+    Core(CoreFuture<V<C>>),
+    Other(C::Future),
+    /// A future wrapping another one, e.g. to record its result.
+    Boxed(Pin<Box<dyn Future<Output = VvvmResult<C>>>>),
 }
 
 pub trait ValueBase: Sized + Trace + Finalize + Clone + Default + 'static {}
 
 pub trait ValueBaseOrdered: ValueBase + PartialEq + Eq + PartialOrd + Ord {}
 
+/// The types with which an embedder extends the values of the core library: built-in functions
+/// that are statically known or created at runtime, synchronous or asynchronous, as well as custom
+/// failures and futures.
+pub trait VvvmConfig: Sized + 'static {
+    type StaticSynchronous: ValueBaseOrdered + BuiltInSynchronousFunction<V<Self>, VvvmFailure<Self>>;
+    type StaticAsynchronous: ValueBaseOrdered + BuiltInAsyncFunction<V<Self>, VvvmFailure<Self>, VvvmFuture<Self>>;
+    type DynamicSynchronous: ValueBase + BuiltInSynchronousFunction<V<Self>, VvvmFailure<Self>>;
+    type DynamicAsynchronous: ValueBase + BuiltInAsyncFunction<V<Self>, VvvmFailure<Self>, VvvmFuture<Self>>;
+    type Failure: 'static;
+    type Future: 'static;
+
+    /// Represent a custom failure as a value, so that scripts can inspect it after catching it
+    /// with `fun_try`. Returns `None` by default, the failure is then represented as `nil`.
+    fn failure_to_value(_failure: &Self::Failure) -> Option<V<Self>> {
+        None
+    }
+
+    /// A name that identifies a static asynchronous function in a `Recording`, so that replaying
+    /// can check that the same functions are invoked. Returns `None` by default, invoking such a
+    /// function while recording then fails with `CoreFailure::Unrecordable`.
+    fn static_asynchronous_name(_f: &Self::StaticAsynchronous) -> Option<String> {
+        None
+    }
+}
+
+/// A configuration given by six type parameters, in the order in which `V` used to take them.
+pub struct GenericConfig<SS, SA, DS, DA, F, Fut>(PhantomData<(SS, SA, DS, DA, F, Fut)>);
+
+impl<SS, SA, DS, DA, F, Fut> VvvmConfig for GenericConfig<SS, SA, DS, DA, F, Fut>
+where
+    SS: ValueBaseOrdered + BuiltInSynchronousFunction<V<Self>, VvvmFailure<Self>>,
+    DS: ValueBase + BuiltInSynchronousFunction<V<Self>, VvvmFailure<Self>>,
+    SA: ValueBaseOrdered + BuiltInAsyncFunction<V<Self>, VvvmFailure<Self>, VvvmFuture<Self>>,
+    DA: ValueBase + BuiltInAsyncFunction<V<Self>, VvvmFailure<Self>, VvvmFuture<Self>>,
+    F: 'static,
+    Fut: 'static,
+{
+    type StaticSynchronous = SS;
+    type StaticAsynchronous = SA;
+    type DynamicSynchronous = DS;
+    type DynamicAsynchronous = DA;
+    type Failure = F;
+    type Future = Fut;
+}
+
+/// The value type with six type parameters, for code written before the introduction of
+/// `VvvmConfig`.
+pub type GenericV<SS, SA, DS, DA, F, Fut> = V<GenericConfig<SS, SA, DS, DA, F, Fut>>;
+
 #[cfg(test)]
 mod tests {
+    use core::cmp::Ordering;
+
+    use crate::{V, DefaultV};
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn total_order_across_types() {
+        let ascending: Vec<DefaultV> = vec![
+            V::nil(),
+            V::boo(false),
+            V::boo(true),
+            V::int(-3),
+            V::int(7),
+            V::float(f64::NAN),
+            V::float(-1.0),
+            V::float(0.5),
+            V::Array(vec![].into_iter().collect()),
+            V::Array(vec![V::int(1)].into_iter().collect()),
+            V::Array(vec![V::int(1), V::int(0)].into_iter().collect()),
+            V::Map(vec![].into_iter().collect()),
+        ];
+
+        for (i, v) in ascending.iter().enumerate() {
+            for (j, w) in ascending.iter().enumerate() {
+                assert_eq!(v.cmp(w), i.cmp(&j), "comparing {:?} and {:?}", v, w);
+            }
+        }
+    }
+
+    #[test]
+    fn partial_order() {
+        let nan: DefaultV = V::float(f64::NAN);
+        assert_eq!(nan.partial_compare(&nan), None);
+        assert_eq!(V::int(1).partial_compare(&V::float(1.0)), None::<Ordering>);
+        assert_eq!(DefaultV::int(1).partial_lt(&V::int(2)), Some(true));
+        assert_eq!(
+            DefaultV::float(0.0).partial_eq(&V::float(-0.0)),
+            Some(true),
+        );
+        assert_eq!(
+            DefaultV::int(4).partial_greatest_lower_bound(&V::int(2)),
+            Some(V::int(2)),
+        );
+        assert_eq!(DefaultV::nil().partial_least_upper_bound(&V::boo(true)), None);
+    }
+
+    #[test]
+    fn strings_and_results() {
+        let s: DefaultV = V::string("hi");
+        assert_eq!(s, V::Array(vec![V::int(104), V::int(105)].into_iter().collect()));
+
+        let ok: DefaultV = V::ok(V::int(1));
+        assert_eq!(ok, V::Map(vec![(V::string("ok"), V::int(1))].into_iter().collect()));
+        assert_ne!(ok, V::err(V::int(1)));
+    }
 }
@@ -0,0 +1,170 @@
+use crate::{V, VvvmConfig};
+
+/// Construct a `V` from literal syntax.
+///
+/// ```
+/// use vvvm_rs::{vvvm, DefaultV};
+///
+/// let some_rust_expression = 42i64;
+/// let v: DefaultV = vvvm!([1, -2.5, "hi", {"a": nil, true: [3]}, (some_rust_expression)]);
+/// assert_eq!(v, vvvm!([1, -2.5, [104, 105], {true: [3], "a": nil}, 42]));
+/// ```
+///
+/// Integer, float, string and boolean literals as well as `nil` map to the corresponding values,
+/// `[...]` builds an array and `{key: value, ...}` a map. A parenthesized Rust expression is
+/// interpolated via `From`, so it can be a `V` or anything convertible into one.
+///
+/// Arrays and maps whose items are all single tokens (anything but negative literals) are built
+/// without recursing per item, so their length is not bounded by the recursion limit. Other arrays
+/// are processed eight literals or eight single-token items at a time where possible, which
+/// allows for roughly a thousand items.
+#[macro_export]
+macro_rules! vvvm {
+    (nil) => { $crate::V::Nil };
+    (true) => { $crate::V::Bool(true) };
+    (false) => { $crate::V::Bool(false) };
+    ([ $($item:tt),* $(,)? ]) => {
+        $crate::V::Array(::std::vec![$($crate::vvvm!($item)),*].into_iter().collect())
+    };
+    ([ $($tt:tt)* ]) => { $crate::V::Array($crate::vvvm!(@array [] $($tt)*)) };
+    ({ $($k:tt : $v:tt),* $(,)? }) => {
+        $crate::V::Map(::std::vec![$(($crate::vvvm!($k), $crate::vvvm!($v))),*].into_iter().collect())
+    };
+    ({ $($tt:tt)* }) => { $crate::V::Map($crate::vvvm!(@map [] $($tt)*)) };
+    (( $e:expr )) => { ::core::convert::From::from($e) };
+    ($lit:literal) => { $crate::macros::Literal::into_v($lit) };
+
+    (@array [$($done:expr,)*]) => {
+        ::std::vec![$($done),*].into_iter().collect()
+    };
+    (@array [$($done:expr,)*]
+        $a:literal, $b:literal, $c:literal, $d:literal,
+        $e:literal, $f:literal, $g:literal, $h:literal $(, $($rest:tt)*)?
+    ) => {
+        $crate::vvvm!(@array [
+            $($done,)*
+            $crate::vvvm!($a), $crate::vvvm!($b), $crate::vvvm!($c), $crate::vvvm!($d),
+            $crate::vvvm!($e), $crate::vvvm!($f), $crate::vvvm!($g), $crate::vvvm!($h),
+        ] $($($rest)*)?)
+    };
+    (@array [$($done:expr,)*]
+        $a:tt, $b:tt, $c:tt, $d:tt, $e:tt, $f:tt, $g:tt, $h:tt $(, $($rest:tt)*)?
+    ) => {
+        $crate::vvvm!(@array [
+            $($done,)*
+            $crate::vvvm!($a), $crate::vvvm!($b), $crate::vvvm!($c), $crate::vvvm!($d),
+            $crate::vvvm!($e), $crate::vvvm!($f), $crate::vvvm!($g), $crate::vvvm!($h),
+        ] $($($rest)*)?)
+    };
+    (@array [$($done:expr,)*] $next:literal $(, $($rest:tt)*)?) => {
+        $crate::vvvm!(@array [$($done,)* $crate::vvvm!($next),] $($($rest)*)?)
+    };
+    (@array [$($done:expr,)*] $next:tt $(, $($rest:tt)*)?) => {
+        $crate::vvvm!(@array [$($done,)* $crate::vvvm!($next),] $($($rest)*)?)
+    };
+
+    (@map [$(($k:expr, $v:expr))*]) => {
+        ::std::vec![$(($k, $v)),*].into_iter().collect()
+    };
+    (@map [$($done:tt)*] $k:literal : $($rest:tt)*) => {
+        $crate::vvvm!(@map_value [$($done)*] ($crate::vvvm!($k)) $($rest)*)
+    };
+    (@map [$($done:tt)*] $k:tt : $($rest:tt)*) => {
+        $crate::vvvm!(@map_value [$($done)*] ($crate::vvvm!($k)) $($rest)*)
+    };
+    (@map_value [$($done:tt)*] ($k:expr) $v:literal $(, $($rest:tt)*)?) => {
+        $crate::vvvm!(@map [$($done)* ($k, $crate::vvvm!($v))] $($($rest)*)?)
+    };
+    (@map_value [$($done:tt)*] ($k:expr) $v:tt $(, $($rest:tt)*)?) => {
+        $crate::vvvm!(@map [$($done)* ($k, $crate::vvvm!($v))] $($($rest)*)?)
+    };
+}
+
+// Implemented for exactly one integer and one float type, so that unsuffixed literals in `vvvm!`
+// infer to `i64` and `f64` respectively.
+#[doc(hidden)]
+pub trait Literal {
+    fn into_v<C: VvvmConfig>(self) -> V<C>;
+}
+
+impl Literal for i64 {
+    fn into_v<C: VvvmConfig>(self) -> V<C> {
+        V::int(self)
+    }
+}
+
+impl Literal for f64 {
+    fn into_v<C: VvvmConfig>(self) -> V<C> {
+        V::float(self)
+    }
+}
+
+impl Literal for bool {
+    fn into_v<C: VvvmConfig>(self) -> V<C> {
+        V::boo(self)
+    }
+}
+
+impl<'a> Literal for &'a str {
+    fn into_v<C: VvvmConfig>(self) -> V<C> {
+        V::string(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{V, DefaultV};
+
+    #[test]
+    fn nested() {
+        let v: DefaultV = vvvm!([[1, [2.5]], {"a": {nil: []}}, -3]);
+        let expected = V::Array(vec![
+            V::Array(vec![V::int(1), V::Array(vec![V::float(2.5)].into_iter().collect())].into_iter().collect()),
+            V::Map(vec![(
+                V::string("a"),
+                V::Map(vec![(V::nil(), V::Array(vec![].into_iter().collect()))].into_iter().collect()),
+            )].into_iter().collect()),
+            V::int(-3),
+        ].into_iter().collect());
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn maps_with_negative_and_interpolated_values() {
+        let x: DefaultV = V::boo(true);
+        let v: DefaultV = vvvm!({1: -1, -2: (x.clone()), "k": [-1.5,],});
+        let expected = V::Map(vec![
+            (V::int(1), V::int(-1)),
+            (V::int(-2), x),
+            (V::string("k"), V::Array(vec![V::float(-1.5)].into_iter().collect())),
+        ].into_iter().collect());
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn long() {
+        let v: DefaultV = vvvm!([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9,
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9,
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9,
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9,
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9,
+        ]);
+        let expected = V::Array((0..150).map(|i| V::int(i % 10)).collect());
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn long_with_negative_literals() {
+        let v: DefaultV = vvvm!([
+            -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1,
+            -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1,
+            -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1,
+            -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1,
+            -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1,
+            -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1, -1, 1,
+        ]);
+        let expected = V::Array((0..144).map(|i| V::int(if i % 2 == 0 { -1 } else { 1 })).collect());
+        assert_eq!(v, expected);
+    }
+}
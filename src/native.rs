@@ -0,0 +1,356 @@
+use std::rc::Rc;
+
+use gc::{Trace, Finalize, unsafe_empty_trace};
+
+use guvm_rs::{BuiltInAsyncFunction, BuiltInSynchronousFunction, Arity, VirtualMachine};
+
+use crate::{V, VvvmConfig, ValueBase, VvvmFailure, VvvmFuture, CoreFailure, CoreFuture, FromValue, IntoValue};
+use crate::fun::{DynamicSynchronous, SynchronousFun, AsynchronousFun, check_arity};
+
+/// A dynamic synchronous function implemented by a Rust closure.
+///
+/// Use it as the `DynamicSynchronous` type of a `VvvmConfig`. The closure must not capture any
+/// garbage-collected values, since they are not traced.
+pub struct NativeSynchronous<C: VvvmConfig> {
+    arity: Arity,
+    fun: Rc<dyn Fn(&[V<C>], &mut VirtualMachine<V<C>>) -> Result<V<C>, VvvmFailure<C>>>,
+}
+
+impl<C: VvvmConfig> NativeSynchronous<C> {
+    /// Wrap a closure that takes up to eight arguments of types implementing `FromValue` and
+    /// returns a `Result` of a type implementing `IntoValue` and an error convertible into the
+    /// failure type of the configuration.
+    ///
+    /// The arity is that of the closure, arguments of the wrong type result in the corresponding
+    /// `CoreFailure`.
+    pub fn new<Args, Fun>(f: Fun) -> Self where
+        Fun: SynchronousAdapter<Args, C>,
+    {
+        f.adapt()
+    }
+
+    /// Wrap a closure that operates directly on the argument slice, which is guaranteed to have
+    /// length `arity`.
+    pub fn from_raw<Fun>(arity: Arity, f: Fun) -> Self where
+        Fun: Fn(&[V<C>], &mut VirtualMachine<V<C>>) -> Result<V<C>, VvvmFailure<C>> + 'static,
+    {
+        NativeSynchronous {
+            arity,
+            fun: Rc::new(f),
+        }
+    }
+
+    pub fn into_dynamic(self) -> DynamicSynchronous<C> where
+        C: VvvmConfig<DynamicSynchronous = Self>,
+    {
+        DynamicSynchronous::Custom(self)
+    }
+
+    pub fn into_fun(self, ordinal: usize) -> SynchronousFun<C> where
+        C: VvvmConfig<DynamicSynchronous = Self>,
+    {
+        SynchronousFun::Dynamic {
+            ordinal,
+            fun: self.into_dynamic(),
+        }
+    }
+}
+
+impl<C: VvvmConfig> Clone for NativeSynchronous<C> {
+    fn clone(&self) -> Self {
+        NativeSynchronous {
+            arity: self.arity,
+            fun: self.fun.clone(),
+        }
+    }
+}
+
+impl<C: VvvmConfig> Default for NativeSynchronous<C> {
+    fn default() -> Self {
+        NativeSynchronous::from_raw(0, |_, _| Ok(V::nil()))
+    }
+}
+
+impl<C: VvvmConfig> Finalize for NativeSynchronous<C> {}
+
+unsafe impl<C: VvvmConfig> Trace for NativeSynchronous<C> {
+    unsafe_empty_trace!();
+}
+
+impl<C: VvvmConfig> ValueBase for NativeSynchronous<C> {}
+
+impl<C: VvvmConfig> BuiltInSynchronousFunction<V<C>, VvvmFailure<C>> for NativeSynchronous<C> {
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn invoke(
+        &mut self,
+        args: &[V<C>],
+        vm: &mut VirtualMachine<V<C>>,
+    ) -> Result<V<C>, VvvmFailure<C>> {
+        check_arity(self.arity, args)?;
+        (self.fun)(args, vm)
+    }
+}
+
+/// Closures that can be turned into a `NativeSynchronous`, `Args` is the tuple of their argument
+/// types.
+pub trait SynchronousAdapter<Args, C: VvvmConfig> {
+    fn adapt(self) -> NativeSynchronous<C>;
+}
+
+/// A dynamic asynchronous function implemented by a Rust closure that returns a future.
+///
+/// Use it as the `DynamicAsynchronous` type of a `VvvmConfig`. The closure must not capture any
+/// garbage-collected values, since they are not traced.
+pub struct NativeAsynchronous<C: VvvmConfig> {
+    arity: Arity,
+    fun: Rc<dyn Fn(&[V<C>], &mut VirtualMachine<V<C>>) -> VvvmFuture<C>>,
+}
+
+impl<C: VvvmConfig> NativeAsynchronous<C> {
+    /// Wrap a closure that takes up to eight arguments of types implementing `FromValue` and
+    /// returns a value convertible into the future type of the configuration.
+    ///
+    /// The arity is that of the closure, arguments of the wrong type result in a core future that
+    /// fails with the corresponding `CoreFailure`.
+    pub fn new<Args, Fun>(f: Fun) -> Self where
+        Fun: AsynchronousAdapter<Args, C>,
+    {
+        f.adapt()
+    }
+
+    /// Wrap a closure that operates directly on the argument slice, which is guaranteed to have
+    /// length `arity`.
+    pub fn from_raw<Fun>(arity: Arity, f: Fun) -> Self where
+        Fun: Fn(&[V<C>], &mut VirtualMachine<V<C>>) -> VvvmFuture<C> + 'static,
+    {
+        NativeAsynchronous {
+            arity,
+            fun: Rc::new(f),
+        }
+    }
+
+    pub fn into_fun(self, ordinal: usize) -> AsynchronousFun<C> where
+        C: VvvmConfig<DynamicAsynchronous = Self>,
+    {
+        AsynchronousFun::Dynamic {
+            ordinal,
+            fun: self,
+        }
+    }
+}
+
+impl<C: VvvmConfig> Clone for NativeAsynchronous<C> {
+    fn clone(&self) -> Self {
+        NativeAsynchronous {
+            arity: self.arity,
+            fun: self.fun.clone(),
+        }
+    }
+}
+
+impl<C: VvvmConfig> Default for NativeAsynchronous<C> {
+    fn default() -> Self {
+        NativeAsynchronous::from_raw(0, |_, _| VvvmFuture::Core(CoreFuture::ready(Ok(V::nil()))))
+    }
+}
+
+impl<C: VvvmConfig> Finalize for NativeAsynchronous<C> {}
+
+unsafe impl<C: VvvmConfig> Trace for NativeAsynchronous<C> {
+    unsafe_empty_trace!();
+}
+
+impl<C: VvvmConfig> ValueBase for NativeAsynchronous<C> {}
+
+impl<C: VvvmConfig> BuiltInAsyncFunction<V<C>, VvvmFailure<C>, VvvmFuture<C>> for NativeAsynchronous<C> {
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn invoke(
+        &mut self,
+        args: &[V<C>],
+        vm: &mut VirtualMachine<V<C>>,
+    ) -> VvvmFuture<C> {
+        if let Err(e) = check_arity(self.arity, args) {
+            return VvvmFuture::Core(CoreFuture::ready(Err(e)));
+        }
+        (self.fun)(args, vm)
+    }
+}
+
+/// Closures that can be turned into a `NativeAsynchronous`, `Args` is the tuple of their argument
+/// types.
+pub trait AsynchronousAdapter<Args, C: VvvmConfig> {
+    fn adapt(self) -> NativeAsynchronous<C>;
+}
+
+macro_rules! adapters {
+    ($arity:expr; $($A:ident $i:tt),*) => (
+        impl<Fun, R, E, $($A,)* C: VvvmConfig> SynchronousAdapter<($($A,)*), C> for Fun where
+            Fun: Fn($($A),*) -> Result<R, E> + 'static,
+            $($A: FromValue<C>,)*
+            R: IntoValue<C>,
+            E: Into<C::Failure>,
+        {
+            #[allow(unused_variables)]
+            fn adapt(self) -> NativeSynchronous<C> {
+                let f = self;
+                NativeSynchronous::from_raw($arity, move |args, _| {
+                    match f($($A::from_value(&args[$i]).map_err(|e| e.at_arg($i))?),*) {
+                        Ok(r) => Ok(r.into_value()),
+                        Err(e) => Err(VvvmFailure::Other(e.into())),
+                    }
+                })
+            }
+        }
+
+        impl<Fun, R, $($A,)* C: VvvmConfig> AsynchronousAdapter<($($A,)*), C> for Fun where
+            Fun: Fn($($A),*) -> R + 'static,
+            $($A: FromValue<C>,)*
+            R: Into<C::Future>,
+        {
+            #[allow(unused_variables)]
+            fn adapt(self) -> NativeAsynchronous<C> {
+                let f = self;
+                NativeAsynchronous::from_raw($arity, move |args, _| {
+                    let convert = || -> Result<($($A,)*), CoreFailure<V<C>>> {
+                        Ok(($($A::from_value(&args[$i]).map_err(|e| e.at_arg($i))?,)*))
+                    };
+                    match convert() {
+                        Ok(converted) => VvvmFuture::Other(f($(converted.$i),*).into()),
+                        Err(e) => VvvmFuture::Core(CoreFuture::ready(Err(e))),
+                    }
+                })
+            }
+        }
+    );
+}
+
+adapters!(0;);
+adapters!(1; A0 0);
+adapters!(2; A0 0, A1 1);
+adapters!(3; A0 0, A1 1, A2 2);
+adapters!(4; A0 0, A1 1, A2 2, A3 3);
+adapters!(5; A0 0, A1 1, A2 2, A3 3, A4 4);
+adapters!(6; A0 0, A1 1, A2 2, A3 3, A4 4, A5 5);
+adapters!(7; A0 0, A1 1, A2 2, A3 3, A4 4, A5 5, A6 6);
+adapters!(8; A0 0, A1 1, A2 2, A3 3, A4 4, A5 5, A6 6, A7 7);
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use guvm_rs::{BuiltInAsyncFunction, BuiltInSynchronousFunction, VirtualMachine};
+
+    use super::{NativeSynchronous, NativeAsynchronous};
+    use crate::{V, VvvmConfig, VvvmFailure, VvvmFuture, VvvmResult, CoreFailure, Never};
+
+    struct TestConfig;
+
+    // Resolves to the wrapped int.
+    struct Ready(i64);
+
+    impl Future for Ready {
+        type Output = VvvmResult<TestConfig>;
+
+        fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<Self::Output> {
+            Poll::Ready(Ok(V::int(self.0)))
+        }
+    }
+
+    impl VvvmConfig for TestConfig {
+        type StaticSynchronous = Never;
+        type StaticAsynchronous = Never;
+        type DynamicSynchronous = NativeSynchronous<TestConfig>;
+        type DynamicAsynchronous = NativeAsynchronous<TestConfig>;
+        type Failure = String;
+        type Future = Ready;
+    }
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn poll_once(mut f: VvvmFuture<TestConfig>) -> Poll<VvvmResult<TestConfig>> {
+        let waker = Waker::from(Arc::new(Noop));
+        Pin::new(&mut f).poll(&mut Context::from_waker(&waker))
+    }
+
+    #[test]
+    fn synchronous() {
+        let mut vm: VirtualMachine<V<TestConfig>> = VirtualMachine::new();
+        let mut f: NativeSynchronous<TestConfig> = NativeSynchronous::new(|n: i64, x: f64| {
+            if x < 0.0 {
+                Err("negative".to_string())
+            } else {
+                Ok(n as f64 * x)
+            }
+        });
+        assert_eq!(BuiltInSynchronousFunction::arity(&f), 2);
+
+        match f.invoke(&[V::int(2), V::float(1.5)], &mut vm) {
+            Ok(V::Float(x)) => assert_eq!(x.0, 3.0),
+            _ => panic!("expected the product"),
+        }
+        match f.invoke(&[V::int(2), V::float(-1.0)], &mut vm) {
+            Err(VvvmFailure::Other(e)) => assert_eq!(e, "negative"),
+            _ => panic!("expected the custom failure"),
+        }
+        match f.invoke(&[V::int(2), V::int(1)], &mut vm) {
+            Err(VvvmFailure::Core(e @ CoreFailure::WrongType { .. })) => assert_eq!(e.at().arg, Some(1)),
+            _ => panic!("expected a wrong type of the second argument"),
+        }
+    }
+
+    #[test]
+    fn asynchronous() {
+        let mut vm: VirtualMachine<V<TestConfig>> = VirtualMachine::new();
+        let mut f: NativeAsynchronous<TestConfig> = NativeAsynchronous::new(|n: i64| Ready(n * 2));
+        assert_eq!(BuiltInAsyncFunction::arity(&f), 1);
+
+        match poll_once(f.invoke(&[V::int(21)], &mut vm)) {
+            Poll::Ready(Ok(V::Int(42))) => {}
+            _ => panic!("expected the doubled int"),
+        }
+
+        let failed = f.invoke(&[V::nil()], &mut vm);
+        assert!(matches!(failed, VvvmFuture::Core(_)));
+        match poll_once(failed) {
+            Poll::Ready(Err(VvvmFailure::Core(e @ CoreFailure::WrongType { .. }))) => {
+                assert_eq!(e.at().arg, Some(0));
+            }
+            _ => panic!("expected a wrong type of the argument"),
+        }
+    }
+
+    #[test]
+    fn arity_mismatch() {
+        let mut vm: VirtualMachine<V<TestConfig>> = VirtualMachine::new();
+
+        let mut f: NativeSynchronous<TestConfig> = NativeSynchronous::new(|n: i64, m: i64| Ok::<_, String>(n + m));
+        match f.invoke(&[V::int(1)], &mut vm) {
+            Err(VvvmFailure::Core(CoreFailure::ArityMismatch { expected: 2, actual: 1, .. })) => {}
+            _ => panic!("expected an arity mismatch"),
+        }
+
+        let mut f: NativeAsynchronous<TestConfig> = NativeAsynchronous::new(|n: i64, m: i64| Ready(n + m));
+        match poll_once(f.invoke(&[V::int(1), V::int(2), V::int(3)], &mut vm)) {
+            Poll::Ready(Err(VvvmFailure::Core(CoreFailure::ArityMismatch { expected: 2, actual: 3, .. }))) => {}
+            _ => panic!("expected an arity mismatch"),
+        }
+        match poll_once(f.invoke(&[], &mut vm)) {
+            Poll::Ready(Err(VvvmFailure::Core(CoreFailure::ArityMismatch { expected: 2, actual: 0, .. }))) => {}
+            _ => panic!("expected an arity mismatch"),
+        }
+    }
+}
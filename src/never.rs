@@ -0,0 +1,128 @@
+use core::cmp::Ordering;
+use core::fmt;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::error::Error;
+
+use gc::{Trace, Finalize, unsafe_empty_trace};
+
+use guvm_rs::{BuiltInAsyncFunction, BuiltInSynchronousFunction, Arity, VirtualMachine};
+
+use crate::{V, VvvmConfig, ValueBase, ValueBaseOrdered, VvvmFailure, VvvmFuture, VvvmResult};
+
+/// An uninhabited type, used for the parts of a `VvvmConfig` that are not needed.
+pub enum Never {}
+
+impl Clone for Never {
+    fn clone(&self) -> Self {
+        match *self {}
+    }
+}
+
+impl PartialEq for Never {
+    fn eq(&self, _: &Self) -> bool {
+        match *self {}
+    }
+}
+
+impl Eq for Never {}
+
+impl PartialOrd for Never {
+    fn partial_cmp(&self, _: &Self) -> Option<Ordering> {
+        match *self {}
+    }
+}
+
+impl Ord for Never {
+    fn cmp(&self, _: &Self) -> Ordering {
+        match *self {}
+    }
+}
+
+/// Panics, since there are no values of type `Never`. It only exists because `ValueBase` requires
+/// `Default`, the core library never calls it.
+impl Default for Never {
+    fn default() -> Self {
+        panic!("there are no values of type `Never`")
+    }
+}
+
+impl fmt::Debug for Never {
+    fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+        match *self {}
+    }
+}
+
+impl fmt::Display for Never {
+    fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+        match *self {}
+    }
+}
+
+impl Error for Never {}
+
+impl Finalize for Never {}
+
+unsafe impl Trace for Never {
+    unsafe_empty_trace!();
+}
+
+impl ValueBase for Never {}
+
+impl ValueBaseOrdered for Never {}
+
+impl<C: VvvmConfig> BuiltInSynchronousFunction<V<C>, VvvmFailure<C>> for Never {
+    fn arity(&self) -> Arity {
+        match *self {}
+    }
+
+    fn invoke(
+        &mut self,
+        _: &[V<C>],
+        _: &mut VirtualMachine<V<C>>,
+    ) -> Result<V<C>, VvvmFailure<C>> {
+        match *self {}
+    }
+}
+
+impl<C: VvvmConfig> BuiltInAsyncFunction<V<C>, VvvmFailure<C>, VvvmFuture<C>> for Never {
+    fn arity(&self) -> Arity {
+        match *self {}
+    }
+
+    fn invoke(
+        &mut self,
+        _: &[V<C>],
+        _: &mut VirtualMachine<V<C>>,
+    ) -> VvvmFuture<C> {
+        match *self {}
+    }
+}
+
+/// An uninhabited future, to be used as the `Future` type of any configuration `C` that does not
+/// need custom futures.
+pub struct NeverFuture<C>(Never, PhantomData<C>);
+
+impl<C: VvvmConfig> Future for NeverFuture<C> {
+    type Output = VvvmResult<C>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<Self::Output> {
+        match self.0 {}
+    }
+}
+
+/// The configuration for using the core library without any extensions.
+pub struct DefaultConfig;
+
+impl VvvmConfig for DefaultConfig {
+    type StaticSynchronous = Never;
+    type StaticAsynchronous = Never;
+    type DynamicSynchronous = Never;
+    type DynamicAsynchronous = Never;
+    type Failure = Never;
+    type Future = NeverFuture<DefaultConfig>;
+}
+
+pub type DefaultV = V<DefaultConfig>;
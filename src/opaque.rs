@@ -0,0 +1,85 @@
+use core::any::Any;
+use core::cmp::Ordering;
+use core::fmt;
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gc::{Trace, Finalize, unsafe_empty_trace};
+
+/// A value that scripts can pass around but not inspect or forge, such as a task handle.
+///
+/// Opaque values are compared by identity.
+///
+/// The payload is shared between all clones and is not traced. Garbage-collected values inside it
+/// therefore stay rooted for as long as the payload exists: they are never freed too early, but
+/// they keep everything they reference alive, and a cycle that runs through a payload (such as a
+/// channel buffering a value that contains the channel itself) is never collected. Hosts that run
+/// untrusted scripts for a long time should bound their lifetime rather than rely on collection.
+#[derive(Clone)]
+pub struct Opaque {
+    id: u64,
+    kind: &'static str,
+    payload: Rc<dyn Any>,
+}
+
+thread_local! {
+    static NEXT_ID: Cell<u64> = Cell::new(0);
+}
+
+impl Opaque {
+    /// Create a new opaque value, `kind` is the name under which scripts see its type.
+    pub fn new<T: Any>(kind: &'static str, payload: T) -> Self {
+        let id = NEXT_ID.with(|next| {
+            let id = next.get();
+            next.set(id + 1);
+            id
+        });
+
+        Opaque {
+            id,
+            kind,
+            payload: Rc::new(payload),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref()
+    }
+}
+
+impl PartialEq for Opaque {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Opaque {}
+
+impl PartialOrd for Opaque {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Opaque {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl fmt::Debug for Opaque {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{} {}>", self.kind, self.id)
+    }
+}
+
+impl Finalize for Opaque {}
+
+// Tracing a shared payload would root and unroot its values once per clone, see above.
+unsafe impl Trace for Opaque {
+    unsafe_empty_trace!();
+}
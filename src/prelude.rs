@@ -0,0 +1,177 @@
+//! The standard environment of a script: all functions of the core library, grouped by module.
+
+use std::collections::BTreeMap;
+
+use crate::{V, VvvmConfig};
+use crate::registry::CoreFunction;
+
+/// A collection of named values organized into modules, convertible into a nested map from module
+/// names to maps from function names to the functions.
+///
+/// `Prelude::core()` contains all core functions, a core function named `int_add` in the module
+/// `int` is available as `{"int": {"add": ...}}`. Hosts can add their own functions and modules on
+/// top of that.
+pub struct Prelude<C: VvvmConfig> {
+    modules: BTreeMap<String, BTreeMap<String, V<C>>>,
+}
+
+impl<C: VvvmConfig> Prelude<C> {
+    /// A prelude without any modules.
+    pub fn empty() -> Self {
+        Prelude {
+            modules: BTreeMap::new(),
+        }
+    }
+
+    /// A prelude containing every function of the core library.
+    pub fn core() -> Self {
+        let mut prelude = Self::empty();
+        for f in CoreFunction::all() {
+            prelude.insert(f.module(), short_name(&f), f.to_value());
+        }
+        prelude
+    }
+
+    /// Add a value under the given module and name, replacing any previous value of that name.
+    pub fn insert(&mut self, module: &str, name: &str, v: V<C>) {
+        self.modules
+            .entry(module.to_string())
+            .or_insert_with(BTreeMap::new)
+            .insert(name.to_string(), v);
+    }
+
+    /// Remove and return the value under the given module and name. Modules that become empty are
+    /// removed as well.
+    pub fn remove(&mut self, module: &str, name: &str) -> Option<V<C>> {
+        let functions = self.modules.get_mut(module)?;
+        let removed = functions.remove(name);
+        if functions.is_empty() {
+            self.modules.remove(module);
+        }
+        removed
+    }
+
+    pub fn get(&self, module: &str, name: &str) -> Option<&V<C>> {
+        self.modules.get(module)?.get(name)
+    }
+
+    /// Keep only the entries for which `f` returns `true`, given the module, name and value.
+    /// Modules that become empty are removed.
+    pub fn retain<F: FnMut(&str, &str, &V<C>) -> bool>(&mut self, mut f: F) {
+        for (module, functions) in self.modules.iter_mut() {
+            let names: Vec<String> = functions
+                .iter()
+                .filter(|(name, v)| !f(module, name, v))
+                .map(|(name, _)| name.clone())
+                .collect();
+            for name in names {
+                functions.remove(&name);
+            }
+        }
+        self.modules.retain(|_, functions| !functions.is_empty());
+    }
+
+    /// The names of all modules, in sorted order.
+    pub fn modules(&self) -> impl Iterator<Item = &str> {
+        self.modules.keys().map(String::as_str)
+    }
+
+    /// Add all entries of `other`, entries of `other` take precedence.
+    pub fn extend(&mut self, other: Prelude<C>) {
+        for (module, functions) in other.modules {
+            for (name, v) in functions {
+                self.insert(&module, &name, v);
+            }
+        }
+    }
+
+    pub fn to_value(&self) -> V<C> {
+        V::Map(self.modules.iter().map(|(module, functions)| {
+            (V::string(module), V::Map(functions.iter().map(|(name, v)| {
+                (V::string(name), v.clone())
+            }).collect()))
+        }).collect())
+    }
+}
+
+impl<C: VvvmConfig> Default for Prelude<C> {
+    fn default() -> Self {
+        Self::core()
+    }
+}
+
+/// The standard prelude as a value: a map from module names to maps from function names to the
+/// core functions.
+pub fn prelude<C: VvvmConfig>() -> V<C> {
+    Prelude::core().to_value()
+}
+
+// The name of a core function within its module, i.e. without the module prefix.
+fn short_name(f: &CoreFunction) -> &'static str {
+    let name = f.name();
+    let module = f.module();
+    if name.len() > module.len() && name.starts_with(module) && name.as_bytes()[module.len()] == b'_' {
+        &name[module.len() + 1..]
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Prelude, prelude, short_name};
+    use crate::{V, DefaultConfig, DefaultV};
+    use crate::registry::CoreFunction;
+    use crate::fun::SynchronousCoreFunction;
+
+    fn entries(prelude: &Prelude<DefaultConfig>) -> usize {
+        match prelude.to_value() {
+            V::Map(modules) => modules.iter().map(|(_, functions)| match functions {
+                V::Map(functions) => functions.len(),
+                _ => panic!("modules are maps"),
+            }).sum(),
+            _ => panic!("the prelude is a map"),
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let core = Prelude::<DefaultConfig>::core();
+        for f in CoreFunction::all() {
+            let name = short_name(&f);
+            assert_eq!(core.get(f.module(), name), Some(&f.to_value()));
+            assert!(name == f.name() || format!("{}_{}", f.module(), name) == f.name());
+        }
+        assert_eq!(entries(&core), CoreFunction::all().count());
+
+        let add = CoreFunction::from(SynchronousCoreFunction::IntAdd).to_value();
+        let int = match prelude::<DefaultConfig>() {
+            V::Map(modules) => modules.get(&V::string("int")).cloned(),
+            _ => None,
+        };
+        match int {
+            Some(V::Map(functions)) => assert_eq!(functions.get(&V::string("add")), Some(&add)),
+            _ => panic!("expected the int module"),
+        }
+    }
+
+    #[test]
+    fn editing() {
+        let mut prelude: Prelude<DefaultConfig> = Prelude::empty();
+        prelude.insert("host", "answer", V::int(42));
+        prelude.insert("host", "question", V::nil());
+        assert_eq!(prelude.modules().collect::<Vec<_>>(), vec!["host"]);
+
+        assert_eq!(prelude.remove("host", "answer"), Some(V::int(42)));
+        assert_eq!(prelude.remove("host", "answer"), None);
+        assert_eq!(prelude.remove("host", "question"), Some(V::nil()));
+        assert_eq!(prelude.modules().count(), 0);
+
+        let mut core = Prelude::<DefaultConfig>::core();
+        let mut overrides = Prelude::empty();
+        overrides.insert("int", "add", DefaultV::nil());
+        core.extend(overrides);
+        assert_eq!(core.get("int", "add"), Some(&V::nil()));
+        assert_eq!(entries(&core), CoreFunction::all().count());
+    }
+}
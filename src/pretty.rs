@@ -0,0 +1,328 @@
+use core::fmt;
+use core::fmt::Write;
+
+use crate::{V, VvvmConfig};
+use crate::fun::Fun;
+
+const HIGHLIGHT_START: &str = "\x1b[1m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+
+/// Configuration for `pretty`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrettyOptions {
+    /// Number of spaces per nesting level.
+    pub indent: usize,
+    /// Maximum line width, arrays and maps are only broken across lines if they exceed it.
+    pub width: usize,
+    /// Nesting depth beyond which arrays and maps are elided as `[...]` and `{...}`.
+    pub max_depth: Option<usize>,
+    /// Number of entries per array or map beyond which the remainder is elided as `...`.
+    pub max_items: Option<usize>,
+    /// Print map entries sorted by their printed keys rather than in the order of the keys as
+    /// values, e.g. `10` before `9`.
+    pub sort_keys: bool,
+    /// Emphasize map keys with ANSI escape codes.
+    pub highlight_keys: bool,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        PrettyOptions {
+            indent: 2,
+            width: 80,
+            max_depth: None,
+            max_items: None,
+            sort_keys: false,
+            highlight_keys: false,
+        }
+    }
+}
+
+/// Render a value as human-readable text, breaking nested arrays and maps only where they do not
+/// fit into the configured width.
+///
+/// Arrays of bytes that form valid utf-8 without control characters (other than newlines and
+/// tabs) are printed as quoted strings, see `V::string`.
+pub fn pretty<C: VvvmConfig>(v: &V<C>, options: &PrettyOptions) -> String {
+    let mut out = String::new();
+    to_doc(v, options, 0).render(options, 0, 0, &mut out);
+    out
+}
+
+// Intermediate representation: the layout decisions need to know the flat width of a subtree
+// before committing to it.
+enum Doc {
+    Text {
+        text: String,
+        // Number of columns occupied by `text`, excluding escape codes.
+        width: usize,
+    },
+    Entry(Box<Doc>, Box<Doc>),
+    Group {
+        open: &'static str,
+        close: &'static str,
+        items: Vec<Doc>,
+    },
+}
+
+impl Doc {
+    fn text(text: String) -> Doc {
+        let width = text.chars().count();
+        Doc::Text { text, width }
+    }
+
+    fn flat_width(&self) -> usize {
+        match self {
+            Doc::Text { width, .. } => *width,
+            Doc::Entry(key, value) => key.flat_width() + 2 + value.flat_width(),
+            Doc::Group { open, close, items } => {
+                let separators = if items.is_empty() { 0 } else { 2 * (items.len() - 1) };
+                open.len() + close.len() + separators
+                    + items.iter().map(Doc::flat_width).sum::<usize>()
+            }
+        }
+    }
+
+    fn render_flat(&self, out: &mut String) {
+        match self {
+            Doc::Text { text, .. } => out.push_str(text),
+            Doc::Entry(key, value) => {
+                key.render_flat(out);
+                out.push_str(": ");
+                value.render_flat(out);
+            }
+            Doc::Group { open, close, items } => {
+                out.push_str(open);
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    item.render_flat(out);
+                }
+                out.push_str(close);
+            }
+        }
+    }
+
+    // `column` is the position at which rendering starts, `level` the current nesting level.
+    fn render(&self, options: &PrettyOptions, level: usize, column: usize, out: &mut String) {
+        if column + self.flat_width() <= options.width {
+            return self.render_flat(out);
+        }
+
+        match self {
+            Doc::Text { text, .. } => out.push_str(text),
+            Doc::Entry(key, value) => {
+                key.render_flat(out);
+                out.push_str(": ");
+                value.render(options, level, column + key.flat_width() + 2, out);
+            }
+            Doc::Group { open, close, items } => {
+                let inner = (level + 1) * options.indent;
+                out.push_str(open);
+                for item in items.iter() {
+                    out.push('\n');
+                    push_spaces(inner, out);
+                    item.render(options, level + 1, inner, out);
+                    out.push(',');
+                }
+                out.push('\n');
+                push_spaces(level * options.indent, out);
+                out.push_str(close);
+            }
+        }
+    }
+}
+
+fn push_spaces(n: usize, out: &mut String) {
+    for _ in 0..n {
+        out.push(' ');
+    }
+}
+
+fn to_doc<C: VvvmConfig>(v: &V<C>, options: &PrettyOptions, depth: usize) -> Doc {
+    if let Some(text) = as_text(v) {
+        return Doc::text(format!("{:?}", text));
+    }
+    let too_deep = options.max_depth.map_or(false, |max| depth >= max);
+
+    match v {
+        V::Nil => Doc::text("nil".to_string()),
+        V::Bool(b) => Doc::text(b.to_string()),
+        V::Int(n) => Doc::text(n.to_string()),
+        V::Float(x) => Doc::text(float_to_string(x.0)),
+        V::Fun(Fun::Closure(_)) => Doc::text("<closure>".to_string()),
+        V::Fun(_) => Doc::text("<built-in>".to_string()),
+        V::Opaque(o) => Doc::text(format!("<{}>", o.kind())),
+
+        V::Array(_) if too_deep => Doc::text("[...]".to_string()),
+        V::Array(a) => {
+            let mut items = Vec::new();
+            for (i, item) in a.iter().enumerate() {
+                if options.max_items.map_or(false, |max| i >= max) {
+                    items.push(Doc::text("...".to_string()));
+                    break;
+                }
+                items.push(to_doc(item, options, depth + 1));
+            }
+            Doc::Group { open: "[", close: "]", items }
+        }
+
+        V::Map(_) if too_deep => Doc::text("{...}".to_string()),
+        V::Map(m) => {
+            // Keys are always printed on a single line.
+            let mut entries: Vec<_> = m.iter().map(|(key, value)| {
+                let mut key_doc = String::new();
+                to_doc(key, options, depth + 1).render_flat(&mut key_doc);
+                (key_doc, value)
+            }).collect();
+            if options.sort_keys {
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+
+            let mut items = Vec::new();
+            for (i, (key_doc, value)) in entries.into_iter().enumerate() {
+                if options.max_items.map_or(false, |max| i >= max) {
+                    items.push(Doc::text("...".to_string()));
+                    break;
+                }
+
+                let key_width = key_doc.chars().count();
+                let key_doc = if options.highlight_keys {
+                    Doc::Text {
+                        text: format!("{}{}{}", HIGHLIGHT_START, key_doc, HIGHLIGHT_END),
+                        width: key_width,
+                    }
+                } else {
+                    Doc::Text { text: key_doc, width: key_width }
+                };
+
+                items.push(Doc::Entry(Box::new(key_doc), Box::new(to_doc(value, options, depth + 1))));
+            }
+            Doc::Group { open: "{", close: "}", items }
+        }
+    }
+}
+
+// The text of a string, i.e. of a non-empty array of bytes that form valid utf-8, unless it
+// contains control characters other than newlines and tabs.
+fn as_text<C: VvvmConfig>(v: &V<C>) -> Option<String> {
+    let a = match v {
+        V::Array(a) if a.len() > 0 => a,
+        _ => return None,
+    };
+
+    let mut bytes = Vec::new();
+    for item in a.iter() {
+        match item {
+            V::Int(n) if 0 <= *n && *n <= 255 => bytes.push(*n as u8),
+            _ => return None,
+        }
+    }
+    let text = String::from_utf8(bytes).ok()?;
+    if text.chars().any(|c| c.is_control() && c != '\n' && c != '\t') {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn float_to_string(x: f64) -> String {
+    if x.is_nan() {
+        "NaN".to_string()
+    } else if x.is_infinite() {
+        if x > 0.0 { "inf".to_string() } else { "-inf".to_string() }
+    } else {
+        let mut s = String::new();
+        write!(s, "{:?}", x).unwrap();
+        s
+    }
+}
+
+impl<C: VvvmConfig> V<C> {
+    pub fn pretty(&self, options: &PrettyOptions) -> String {
+        pretty(self, options)
+    }
+}
+
+/// Renders the value on a single line.
+impl<C: VvvmConfig> fmt::Debug for V<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let options = PrettyOptions {
+            width: usize::MAX,
+            ..PrettyOptions::default()
+        };
+        f.write_str(&pretty(self, &options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pretty, PrettyOptions};
+    use crate::{V, DefaultV};
+
+    fn array(items: Vec<DefaultV>) -> DefaultV {
+        V::Array(items.into_iter().collect())
+    }
+
+    fn map(entries: Vec<(DefaultV, DefaultV)>) -> DefaultV {
+        V::Map(entries.into_iter().collect())
+    }
+
+    #[test]
+    fn fits_on_one_line() {
+        let v = array(vec![V::int(1), V::float(2.5), V::nil(), map(vec![(V::boo(true), V::int(3))])]);
+        assert_eq!(pretty(&v, &PrettyOptions::default()), "[1, 2.5, nil, {true: 3}]");
+    }
+
+    #[test]
+    fn breaks_only_what_does_not_fit() {
+        let v = array(vec![array(vec![V::int(1), V::int(2)]), array(vec![V::int(100000), V::int(200000)])]);
+        let options = PrettyOptions { width: 20, ..PrettyOptions::default() };
+        assert_eq!(pretty(&v, &options), "[\n  [1, 2],\n  [100000, 200000],\n]");
+    }
+
+    #[test]
+    fn truncates() {
+        let v = array(vec![V::int(1), array(vec![V::int(2)]), V::int(3)]);
+        let options = PrettyOptions {
+            max_depth: Some(1),
+            max_items: Some(2),
+            ..PrettyOptions::default()
+        };
+        assert_eq!(pretty(&v, &options), "[1, [...], ...]");
+    }
+
+    #[test]
+    fn strings() {
+        let v = map(vec![
+            (V::string("name"), V::string("say \"hi\"\n")),
+            (V::string("n"), array(vec![V::int(1), V::int(2)])),
+        ]);
+        assert_eq!(pretty(&v, &PrettyOptions::default()), "{\"n\": [1, 2], \"name\": \"say \\\"hi\\\"\\n\"}");
+
+        // Empty arrays, invalid utf-8 and control characters are printed as arrays.
+        let v = array(vec![array(vec![]), array(vec![V::int(255)]), array(vec![V::int(0)])]);
+        assert_eq!(pretty(&v, &PrettyOptions::default()), "[[], [255], [0]]");
+    }
+
+    #[test]
+    fn sorts_keys_by_their_text() {
+        let v = map(vec![(V::int(9), V::nil()), (V::int(10), V::nil()), (V::string("a"), V::nil())]);
+        assert_eq!(pretty(&v, &PrettyOptions::default()), "{9: nil, 10: nil, \"a\": nil}");
+
+        let options = PrettyOptions { sort_keys: true, ..PrettyOptions::default() };
+        assert_eq!(pretty(&v, &options), "{\"a\": nil, 10: nil, 9: nil}");
+    }
+
+    #[test]
+    fn highlights_keys() {
+        let v = map(vec![(V::int(1), V::int(2))]);
+        let options = PrettyOptions { highlight_keys: true, width: 6, ..PrettyOptions::default() };
+        // The escape codes do not count towards the width.
+        assert_eq!(pretty(&v, &options), "{\x1b[1m1\x1b[0m: 2}");
+
+        let options = PrettyOptions { width: 5, ..options };
+        assert_eq!(pretty(&v, &options), "{\n  \x1b[1m1\x1b[0m: 2,\n}");
+    }
+}
@@ -0,0 +1,417 @@
+//! Recording the arguments and results of all asynchronous built-in functions, and replaying them
+//! to reproduce a run deterministically.
+//!
+//! While a `Recorder` is entered on a thread, every invocation of an `AsynchronousFun` on that
+//! thread goes through it. When recording, invocations are logged in the order in which they
+//! happen, with their results once they complete. When replaying, functions of the configuration
+//! are not invoked at all, they complete with the recorded results instead. Core functions are
+//! still invoked, since with a `VirtualClock` they behave the same in every run. Every invocation
+//! is checked against the recording, a mismatch fails with `CoreFailure::Diverged`.
+//!
+//! Recording fails with `CoreFailure::Unrecordable` where replaying could not work: for static
+//! asynchronous functions that the configuration does not name (see
+//! `VvvmConfig::static_asynchronous_name`), and for functions of the configuration whose results
+//! contain values that the encoding cannot reconstruct, such as closures and opaque values.
+
+use core::any::Any;
+use core::cell::{Cell, RefCell};
+use core::future::Future;
+use core::pin::Pin;
+use std::rc::Rc;
+
+use guvm_rs::VirtualMachine;
+
+use crate::{V, VvvmConfig, VvvmFailure, VvvmFuture, VvvmResult, AsynchronousFun, CoreFailure, CoreFuture};
+use crate::encoding::{self, DecodeError};
+
+/// One invocation of an asynchronous function, with all values in the `encoding` format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The name of a core function, or `"static <name>"` or `"dynamic <ordinal>"` for functions of
+    /// the configuration.
+    pub function: String,
+    /// The arguments as an array.
+    pub arguments: Vec<u8>,
+    /// The value or the failure (as a value), `None` if the function never completed.
+    pub result: Option<Result<Vec<u8>, Vec<u8>>>,
+}
+
+/// The log of a recorded run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Recording {
+    pub entries: Vec<Entry>,
+}
+
+impl Recording {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        encoding::write_length(self.entries.len(), &mut out);
+        for entry in self.entries.iter() {
+            encoding::write_bytes(entry.function.as_bytes(), &mut out);
+            encoding::write_bytes(&entry.arguments, &mut out);
+            match &entry.result {
+                None => out.push(0),
+                Some(Ok(v)) => {
+                    out.push(1);
+                    encoding::write_bytes(v, &mut out);
+                }
+                Some(Err(e)) => {
+                    out.push(2);
+                    encoding::write_bytes(e, &mut out);
+                }
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut input = bytes;
+        let len = encoding::read_length(&mut input)?;
+        let mut entries = Vec::new();
+        for _ in 0..len {
+            let function = String::from_utf8(encoding::read_bytes(&mut input)?.to_vec())
+                .map_err(|_| DecodeError::InvalidUtf8)?;
+            let arguments = encoding::read_bytes(&mut input)?.to_vec();
+            let result = match encoding::read_u8(&mut input)? {
+                0 => None,
+                1 => Some(Ok(encoding::read_bytes(&mut input)?.to_vec())),
+                2 => Some(Err(encoding::read_bytes(&mut input)?.to_vec())),
+                tag => return Err(DecodeError::UnknownTag(tag)),
+            };
+            entries.push(Entry { function, arguments, result });
+        }
+
+        if input.is_empty() {
+            Ok(Recording { entries })
+        } else {
+            Err(DecodeError::TrailingBytes)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Record,
+    Replay,
+}
+
+/// Records or replays the asynchronous functions invoked on the current thread while entered.
+/// Clones share the same recording.
+pub struct Recorder<C: VvvmConfig> {
+    inner: Rc<Inner<C>>,
+}
+
+struct Inner<C: VvvmConfig> {
+    mode: Mode,
+    recording: RefCell<Recording>,
+    // The index of the next entry to replay.
+    position: Cell<usize>,
+    // Erases the futures of the configuration, which are only known to be futures where the
+    // recorder is created.
+    boxed: fn(VvvmFuture<C>) -> Pin<Box<dyn Future<Output = VvvmResult<C>>>>,
+}
+
+fn boxed<C: VvvmConfig>(f: VvvmFuture<C>) -> Pin<Box<dyn Future<Output = VvvmResult<C>>>> where
+    C::Future: Future<Output = VvvmResult<C>>,
+{
+    Box::pin(f)
+}
+
+impl<C: VvvmConfig> Recorder<C> where
+    C::Future: Future<Output = VvvmResult<C>>,
+{
+    /// A recorder that starts with an empty recording.
+    pub fn record() -> Self {
+        Self::new(Mode::Record, Recording::default())
+    }
+
+    /// A recorder that replays `recording`.
+    pub fn replay(recording: Recording) -> Self {
+        Self::new(Mode::Replay, recording)
+    }
+
+    fn new(mode: Mode, recording: Recording) -> Self {
+        Recorder {
+            inner: Rc::new(Inner {
+                mode,
+                recording: RefCell::new(recording),
+                position: Cell::new(0),
+                boxed: boxed::<C>,
+            }),
+        }
+    }
+}
+
+impl<C: VvvmConfig> Recorder<C> {
+    /// Route the asynchronous functions invoked on the current thread through this recorder
+    /// until the returned guard is dropped, at which point the previously entered recorder (if
+    /// any) takes over again.
+    pub fn enter(&self) -> RecorderGuard {
+        let inner: Rc<dyn Any> = self.inner.clone();
+        let previous = ACTIVE.with(|active| active.replace(Some(inner)));
+        RecorderGuard { previous }
+    }
+
+    /// A copy of the recording so far.
+    pub fn recording(&self) -> Recording {
+        self.inner.recording.borrow().clone()
+    }
+
+    /// Whether all recorded invocations have been replayed. Always `true` when recording.
+    pub fn is_exhausted(&self) -> bool {
+        self.inner.mode == Mode::Record
+            || self.inner.position.get() >= self.inner.recording.borrow().entries.len()
+    }
+}
+
+impl<C: VvvmConfig> Clone for Recorder<C> {
+    fn clone(&self) -> Self {
+        Recorder { inner: self.inner.clone() }
+    }
+}
+
+/// Restores the previously entered recorder when dropped.
+pub struct RecorderGuard {
+    previous: Option<Rc<dyn Any>>,
+}
+
+impl Drop for RecorderGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACTIVE.with(|active| active.replace(previous));
+    }
+}
+
+thread_local! {
+    // An `Inner<C>`.
+    static ACTIVE: RefCell<Option<Rc<dyn Any>>> = RefCell::new(None);
+}
+
+fn active<C: VvvmConfig>() -> Option<Rc<Inner<C>>> {
+    ACTIVE.with(|active| active.borrow().clone()?.downcast::<Inner<C>>().ok())
+}
+
+fn label<C: VvvmConfig>(f: &AsynchronousFun<C>) -> Result<String, CoreFailure<V<C>>> {
+    match f {
+        AsynchronousFun::Core(f) => Ok(f.name().to_string()),
+        AsynchronousFun::StaticAsynchronous(f) => match C::static_asynchronous_name(f) {
+            Some(name) => Ok(format!("static {}", name)),
+            None => Err(CoreFailure::unrecordable(
+                "the configuration does not name its static asynchronous functions".to_string(),
+            )),
+        },
+        AsynchronousFun::Dynamic { ordinal, .. } => Ok(format!("dynamic {}", ordinal)),
+    }
+}
+
+/// Invoke `f`, going through the entered recorder if there is one.
+pub(crate) fn invoke<C: VvvmConfig>(
+    f: &mut AsynchronousFun<C>,
+    args: &[V<C>],
+    vm: &mut VirtualMachine<V<C>>,
+) -> VvvmFuture<C> {
+    let recorder = match active::<C>() {
+        Some(recorder) => recorder,
+        None => return f.invoke_unrecorded(args, vm),
+    };
+
+    let function = match label(f) {
+        Ok(function) => function,
+        Err(e) => return VvvmFuture::Core(CoreFuture::ready(Err(e))),
+    };
+    let arguments = encoding::encode(&V::Array(args.iter().cloned().collect()));
+
+    match recorder.mode {
+        Mode::Record => {
+            let index = {
+                let mut recording = recorder.recording.borrow_mut();
+                recording.entries.push(Entry { function: function.clone(), arguments, result: None });
+                recording.entries.len() - 1
+            };
+
+            // Only the results of functions of the configuration are decoded when replaying.
+            let replayed = !matches!(f, AsynchronousFun::Core(_));
+            let future = (recorder.boxed)(f.invoke_unrecorded(args, vm));
+            VvvmFuture::Boxed(Box::pin(async move {
+                let mut result = future.await;
+                let decodable = match &result {
+                    Ok(v) => encoding::is_decodable(v),
+                    Err(e) => encoding::is_decodable(&e.to_value()),
+                };
+                if replayed && !decodable {
+                    result = Err(VvvmFailure::Core(CoreFailure::unrecordable(format!(
+                        "the result of `{}` contains values that cannot be replayed", function,
+                    ))));
+                }
+
+                recorder.recording.borrow_mut().entries[index].result = Some(match &result {
+                    Ok(v) => Ok(encoding::encode(v)),
+                    Err(e) => Err(encoding::encode(&e.to_value())),
+                });
+                result
+            }))
+        }
+
+        Mode::Replay => {
+            let index = recorder.position.get();
+            recorder.position.set(index + 1);
+            let entry = recorder.recording.borrow().entries.get(index).cloned();
+
+            let entry = match entry {
+                None => return diverged(format!("invoked `{}` after the end of the recording", function)),
+                Some(entry) if entry.function != function => {
+                    return diverged(format!("invoked `{}` instead of `{}`", function, entry.function));
+                }
+                Some(entry) if entry.arguments != arguments => {
+                    return diverged(format!("invoked `{}` with different arguments", function));
+                }
+                Some(entry) => entry,
+            };
+
+            if let AsynchronousFun::Core(_) = f {
+                return f.invoke_unrecorded(args, vm);
+            }
+
+            match entry.result {
+                // Never completed in the recorded run either.
+                None => VvvmFuture::Boxed(Box::pin(core::future::pending())),
+                Some(Ok(v)) => match encoding::decode(&v) {
+                    Ok(v) => VvvmFuture::Core(CoreFuture::ready(Ok(v))),
+                    Err(e) => diverged(format!("cannot replay the result of `{}`: {}", function, e)),
+                },
+                Some(Err(e)) => match encoding::decode(&e) {
+                    Ok(e) => VvvmFuture::Core(CoreFuture::ready(Err(CoreFailure::recorded(e)))),
+                    Err(e) => diverged(format!("cannot replay the failure of `{}`: {}", function, e)),
+                },
+            }
+        }
+    }
+}
+
+fn diverged<C: VvvmConfig>(reason: String) -> VvvmFuture<C> {
+    VvvmFuture::Core(CoreFuture::ready(Err(CoreFailure::diverged(reason))))
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use guvm_rs::VirtualMachine;
+
+    use super::{invoke, Recorder, Recording};
+    use crate::{V, VvvmConfig, VvvmFailure, VvvmFuture, VvvmResult, AsynchronousFun, CoreFailure, CoreFuture, Never, NeverFuture, Opaque};
+    use crate::native::{NativeSynchronous, NativeAsynchronous};
+
+    struct TestConfig;
+
+    impl VvvmConfig for TestConfig {
+        type StaticSynchronous = Never;
+        type StaticAsynchronous = Never;
+        type DynamicSynchronous = NativeSynchronous<TestConfig>;
+        type DynamicAsynchronous = NativeAsynchronous<TestConfig>;
+        type Failure = Never;
+        type Future = NeverFuture<TestConfig>;
+    }
+
+    type TestV = V<TestConfig>;
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn call(f: &mut AsynchronousFun<TestConfig>, args: &[TestV]) -> VvvmResult<TestConfig> {
+        let mut vm: VirtualMachine<TestV> = VirtualMachine::new();
+        let mut future = invoke(f, args, &mut vm);
+        let waker = Waker::from(Arc::new(Noop));
+        match Pin::new(&mut future).poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(result) => result,
+            Poll::Pending => panic!("the test functions complete immediately"),
+        }
+    }
+
+    // Returns how often it has been invoked before, plus its argument.
+    fn counter(invocations: Rc<Cell<i64>>) -> AsynchronousFun<TestConfig> {
+        NativeAsynchronous::from_raw(1, move |args: &[TestV], _: &mut VirtualMachine<TestV>| {
+            let n = invocations.get();
+            invocations.set(n + 1);
+            let result = match &args[0] {
+                V::Int(m) => Ok(V::int(n + m)),
+                v => Err(CoreFailure::halt(v.clone())),
+            };
+            VvvmFuture::Core(CoreFuture::ready(result))
+        }).into_fun(0)
+    }
+
+    fn record(f: &mut AsynchronousFun<TestConfig>, args: &[TestV]) -> Recording {
+        let recorder = Recorder::<TestConfig>::record();
+        let _guard = recorder.enter();
+        for arg in args {
+            let _ = call(f, &[arg.clone()]);
+        }
+        recorder.recording()
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let invocations = Rc::new(Cell::new(0));
+        let mut f = counter(invocations.clone());
+        let recording = record(&mut f, &[V::int(10), V::nil()]);
+        assert_eq!(invocations.get(), 2);
+        assert_eq!(Recording::from_bytes(&recording.to_bytes()), Ok(recording.clone()));
+
+        let recorder = Recorder::<TestConfig>::replay(recording);
+        let _guard = recorder.enter();
+        assert!(matches!(call(&mut f, &[V::int(10)]), Ok(V::Int(10))));
+        match call(&mut f, &[V::nil()]) {
+            Err(VvvmFailure::Core(CoreFailure::Recorded { value, .. })) => {
+                assert_eq!(value, CoreFailure::halt(V::nil()).to_value());
+            }
+            _ => panic!("expected the recorded failure"),
+        }
+        assert!(recorder.is_exhausted());
+        assert_eq!(invocations.get(), 2);
+    }
+
+    #[test]
+    fn divergence() {
+        let mut f = counter(Rc::new(Cell::new(0)));
+        let recording = record(&mut f, &[V::int(1)]);
+
+        let recorder = Recorder::<TestConfig>::replay(recording.clone());
+        let _guard = recorder.enter();
+        assert!(matches!(call(&mut f, &[V::int(2)]), Err(VvvmFailure::Core(CoreFailure::Diverged { .. }))));
+        assert!(matches!(call(&mut f, &[V::int(1)]), Err(VvvmFailure::Core(CoreFailure::Diverged { .. }))));
+
+        let recorder = Recorder::<TestConfig>::replay(recording);
+        let _guard = recorder.enter();
+        let mut other = counter(Rc::new(Cell::new(0)));
+        if let AsynchronousFun::Dynamic { ordinal, .. } = &mut other {
+            *ordinal = 1;
+        }
+        assert!(matches!(call(&mut other, &[V::int(1)]), Err(VvvmFailure::Core(CoreFailure::Diverged { .. }))));
+    }
+
+    #[test]
+    fn unrecordable_results() {
+        let mut f: AsynchronousFun<TestConfig> = NativeAsynchronous::from_raw(0, |_: &[TestV], _: &mut VirtualMachine<TestV>| {
+            VvvmFuture::Core(CoreFuture::ready(Ok(V::Opaque(Opaque::new("thing", ())))))
+        }).into_fun(0);
+
+        let recorder = Recorder::<TestConfig>::record();
+        let _guard = recorder.enter();
+        assert!(matches!(call(&mut f, &[]), Err(VvvmFailure::Core(CoreFailure::Unrecordable { .. }))));
+
+        match &recorder.recording().entries[0].result {
+            Some(Err(e)) => assert!(crate::encoding::decode::<TestConfig>(e).is_ok()),
+            _ => panic!("expected a recorded failure"),
+        }
+    }
+}
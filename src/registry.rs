@@ -0,0 +1,118 @@
+//! Lookup of the core functions by name.
+//!
+//! The metadata lives next to the function definitions in the `core_functions!` tables, so every
+//! function of the core library is automatically part of the registry.
+
+use guvm_rs::Arity;
+
+use crate::{V, VvvmConfig};
+use crate::fun::{Fun, SynchronousFun, AsynchronousFun, SynchronousCoreFunction, AsynchronousCoreFunction};
+
+/// Any function of the core library, synchronous or asynchronous.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum CoreFunction {
+    Synchronous(SynchronousCoreFunction),
+    Asynchronous(AsynchronousCoreFunction),
+}
+
+impl CoreFunction {
+    /// All core functions, synchronous ones first, each in declaration order.
+    pub fn all() -> impl Iterator<Item = CoreFunction> {
+        SynchronousCoreFunction::ALL.iter().cloned().map(CoreFunction::Synchronous)
+            .chain(AsynchronousCoreFunction::ALL.iter().cloned().map(CoreFunction::Asynchronous))
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match SynchronousCoreFunction::from_name(name) {
+            Some(f) => Some(CoreFunction::Synchronous(f)),
+            None => AsynchronousCoreFunction::from_name(name).map(CoreFunction::Asynchronous),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CoreFunction::Synchronous(f) => f.name(),
+            CoreFunction::Asynchronous(f) => f.name(),
+        }
+    }
+
+    pub fn module(&self) -> &'static str {
+        match self {
+            CoreFunction::Synchronous(f) => f.module(),
+            CoreFunction::Asynchronous(f) => f.module(),
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
+        match self {
+            CoreFunction::Synchronous(f) => f.arity(),
+            CoreFunction::Asynchronous(f) => f.arity(),
+        }
+    }
+
+    pub fn is_pure(&self) -> bool {
+        match self {
+            CoreFunction::Synchronous(f) => f.is_pure(),
+            CoreFunction::Asynchronous(f) => f.is_pure(),
+        }
+    }
+
+    pub fn is_asynchronous(&self) -> bool {
+        match self {
+            CoreFunction::Synchronous(_) => false,
+            CoreFunction::Asynchronous(_) => true,
+        }
+    }
+
+    /// The function as a value.
+    pub fn to_fun<C: VvvmConfig>(&self) -> Fun<C> {
+        match self {
+            CoreFunction::Synchronous(f) => Fun::SynchronousFunction(SynchronousFun::Core(f.clone())),
+            CoreFunction::Asynchronous(f) => Fun::AsynchronousFunction(AsynchronousFun::Core(f.clone())),
+        }
+    }
+
+    pub fn to_value<C: VvvmConfig>(&self) -> V<C> {
+        V::Fun(self.to_fun())
+    }
+}
+
+impl From<SynchronousCoreFunction> for CoreFunction {
+    fn from(f: SynchronousCoreFunction) -> Self {
+        CoreFunction::Synchronous(f)
+    }
+}
+
+impl From<AsynchronousCoreFunction> for CoreFunction {
+    fn from(f: AsynchronousCoreFunction) -> Self {
+        CoreFunction::Asynchronous(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::CoreFunction;
+    use crate::fun::{SynchronousCoreFunction, AsynchronousCoreFunction};
+
+    #[test]
+    fn names_round_trip() {
+        for f in CoreFunction::all() {
+            assert_eq!(CoreFunction::from_name(f.name()), Some(f.clone()));
+        }
+        assert_eq!(CoreFunction::from_name("int_frobnicate"), None);
+    }
+
+    #[test]
+    fn all_functions() {
+        let all: Vec<CoreFunction> = CoreFunction::all().collect();
+        assert_eq!(all.len(), SynchronousCoreFunction::ALL.len() + AsynchronousCoreFunction::ALL.len());
+
+        let names: BTreeSet<&str> = all.iter().map(CoreFunction::name).collect();
+        assert_eq!(names.len(), all.len());
+        for f in &all {
+            assert_eq!(f.is_asynchronous(), matches!(f, CoreFunction::Asynchronous(_)));
+        }
+    }
+}
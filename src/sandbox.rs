@@ -0,0 +1,302 @@
+//! Restricting which core functions untrusted scripts may use.
+//!
+//! A `Sandbox` is enforced in two places: it filters the prelude handed to a script, and while it
+//! is active on the current thread (see `Sandbox::enter`), invoking a disallowed core function
+//! fails with `CoreFailure::Disallowed`. The latter also covers function values that a script
+//! obtains in other ways than through the prelude. Use `Sandbox::confine` to keep a sandbox
+//! active for an asynchronous run, tasks that the script spawns inherit it.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+use crate::{V, VvvmConfig, CoreFailure, Fun, SynchronousFun, AsynchronousFun};
+use crate::prelude::Prelude;
+use crate::registry::CoreFunction;
+use crate::fun::SynchronousCoreFunction;
+
+/// Which core functions are available. Functions supplied by the host are never restricted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sandbox {
+    pure_only: bool,
+    asynchronous: bool,
+    // `None` allows everything that is not denied.
+    allowed: Option<BTreeSet<CoreFunction>>,
+    denied: BTreeSet<CoreFunction>,
+}
+
+impl Sandbox {
+    /// Allows all core functions.
+    pub fn unrestricted() -> Self {
+        Sandbox {
+            pure_only: false,
+            asynchronous: true,
+            allowed: None,
+            denied: BTreeSet::new(),
+        }
+    }
+
+    /// Allows only the pure core functions.
+    pub fn pure_only() -> Self {
+        Self::unrestricted().pure()
+    }
+
+    /// Allows all core functions except `value_halt`.
+    pub fn no_halt() -> Self {
+        Self::unrestricted().deny(vec![SynchronousCoreFunction::ValueHalt.into()])
+    }
+
+    /// Allows all synchronous core functions.
+    pub fn no_async() -> Self {
+        Self::unrestricted().synchronous_only()
+    }
+
+    /// Allows exactly the given core functions.
+    pub fn allow_list<I: IntoIterator<Item = CoreFunction>>(functions: I) -> Self {
+        Self::unrestricted().allow_only(functions)
+    }
+
+    /// Allows all core functions except the given ones.
+    pub fn deny_list<I: IntoIterator<Item = CoreFunction>>(functions: I) -> Self {
+        Self::unrestricted().deny(functions)
+    }
+
+    /// Additionally disallow all impure functions.
+    pub fn pure(mut self) -> Self {
+        self.pure_only = true;
+        self
+    }
+
+    /// Additionally disallow all asynchronous functions.
+    pub fn synchronous_only(mut self) -> Self {
+        self.asynchronous = false;
+        self
+    }
+
+    /// Additionally disallow all functions not in `functions`.
+    pub fn allow_only<I: IntoIterator<Item = CoreFunction>>(mut self, functions: I) -> Self {
+        let functions: BTreeSet<_> = functions.into_iter().collect();
+        self.allowed = Some(match self.allowed {
+            None => functions,
+            Some(allowed) => allowed.intersection(&functions).cloned().collect(),
+        });
+        self
+    }
+
+    /// Additionally disallow the given functions.
+    pub fn deny<I: IntoIterator<Item = CoreFunction>>(mut self, functions: I) -> Self {
+        self.denied.extend(functions);
+        self
+    }
+
+    pub fn allows(&self, f: &CoreFunction) -> bool {
+        !(self.pure_only && !f.is_pure())
+            && !(!self.asynchronous && f.is_asynchronous())
+            && self.allowed.as_ref().map_or(true, |allowed| allowed.contains(f))
+            && !self.denied.contains(f)
+    }
+
+    /// Whether the value may be invoked, i.e. it is not a disallowed core function.
+    pub fn allows_value<C: VvvmConfig>(&self, v: &V<C>) -> bool {
+        match v {
+            V::Fun(Fun::SynchronousFunction(SynchronousFun::Core(f))) => self.allows(&f.clone().into()),
+            V::Fun(Fun::AsynchronousFunction(AsynchronousFun::Core(f))) => self.allows(&f.clone().into()),
+            _ => true,
+        }
+    }
+
+    /// Remove all disallowed core functions from the prelude.
+    pub fn filter<C: VvvmConfig>(&self, prelude: &mut Prelude<C>) {
+        prelude.retain(|_, _, v| self.allows_value(v));
+    }
+
+    /// The core prelude restricted to the allowed functions.
+    pub fn prelude<C: VvvmConfig>(&self) -> Prelude<C> {
+        let mut prelude = Prelude::core();
+        self.filter(&mut prelude);
+        prelude
+    }
+
+    /// Enforce this sandbox on the current thread until the returned guard is dropped, at which
+    /// point the previously active sandbox (if any) is restored.
+    pub fn enter(self) -> SandboxGuard {
+        let previous = ACTIVE.with(|active| active.replace(Some(self)));
+        SandboxGuard { previous }
+    }
+
+    /// Wrap `f` so that this sandbox is active whenever it is polled.
+    pub fn confine<F: Future>(self, f: F) -> Confined<F> {
+        Confined {
+            sandbox: self,
+            future: Box::pin(f),
+        }
+    }
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::unrestricted()
+    }
+}
+
+/// Restores the previously active sandbox when dropped.
+pub struct SandboxGuard {
+    previous: Option<Sandbox>,
+}
+
+impl Drop for SandboxGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACTIVE.with(|active| active.replace(previous));
+    }
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<Sandbox>> = RefCell::new(None);
+}
+
+/// A future with an active `Sandbox`, see `Sandbox::confine`.
+pub struct Confined<F> {
+    sandbox: Sandbox,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Confined<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        let _guard = self.sandbox.clone().enter();
+        self.future.as_mut().poll(cx)
+    }
+}
+
+pub(crate) fn active() -> Option<Sandbox> {
+    ACTIVE.with(|active| active.borrow().clone())
+}
+
+/// Fail with `CoreFailure::Disallowed` if the active sandbox does not allow `f`.
+pub(crate) fn check<Val>(f: CoreFunction) -> Result<(), CoreFailure<Val>> {
+    let allowed = ACTIVE.with(|active| {
+        active.borrow().as_ref().map_or(true, |sandbox| sandbox.allows(&f))
+    });
+    if allowed {
+        Ok(())
+    } else {
+        Err(CoreFailure::disallowed(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use guvm_rs::{BuiltInAsyncFunction, BuiltInSynchronousFunction, VirtualMachine};
+
+    use super::Sandbox;
+    use crate::{V, DefaultConfig, DefaultV, VvvmFailure, CoreFailure, Fun};
+    use crate::registry::CoreFunction;
+    use crate::fun::{SynchronousCoreFunction, AsynchronousCoreFunction};
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn add() -> CoreFunction {
+        SynchronousCoreFunction::IntAdd.into()
+    }
+
+    fn halt() -> CoreFunction {
+        SynchronousCoreFunction::ValueHalt.into()
+    }
+
+    fn yield_() -> CoreFunction {
+        AsynchronousCoreFunction::PreemptiveYield.into()
+    }
+
+    // Invoke `int_add` as a script would, given the function as a value.
+    fn invoke_add(vm: &mut VirtualMachine<DefaultV>) -> Result<DefaultV, VvvmFailure<DefaultConfig>> {
+        match CoreFunction::from_name("int_add").unwrap().to_value::<DefaultConfig>() {
+            V::Fun(Fun::SynchronousFunction(mut f)) => f.invoke(&[V::int(1), V::int(2)], vm),
+            _ => panic!("int_add is synchronous"),
+        }
+    }
+
+    #[test]
+    fn profiles() {
+        let unrestricted = Sandbox::unrestricted();
+        assert!(CoreFunction::all().all(|f| unrestricted.allows(&f)));
+
+        let pure = Sandbox::pure_only();
+        assert!(CoreFunction::all().all(|f| pure.allows(&f) == f.is_pure()));
+
+        let no_halt = Sandbox::no_halt();
+        assert!(!no_halt.allows(&halt()));
+        assert!(no_halt.allows(&add()) && no_halt.allows(&yield_()));
+
+        let no_async = Sandbox::no_async();
+        assert!(CoreFunction::all().all(|f| no_async.allows(&f) != f.is_asynchronous()));
+
+        let allowed = Sandbox::allow_list(vec![add(), halt()]);
+        assert!(CoreFunction::all().all(|f| allowed.allows(&f) == (f == add() || f == halt())));
+        let narrowed = allowed.allow_only(vec![add(), yield_()]);
+        assert!(CoreFunction::all().all(|f| narrowed.allows(&f) == (f == add())));
+
+        let denied = Sandbox::deny_list(vec![add()]);
+        assert!(CoreFunction::all().all(|f| denied.allows(&f) == (f != add())));
+        assert!(!denied.clone().pure().allows(&halt()));
+        assert!(!denied.allows_value::<DefaultConfig>(&add().to_value()));
+        assert!(denied.allows_value::<DefaultConfig>(&V::int(1)));
+    }
+
+    #[test]
+    fn prelude() {
+        let prelude = Sandbox::no_halt().prelude::<DefaultConfig>();
+        assert_eq!(prelude.get("value", "halt"), None);
+        assert_eq!(prelude.get("int", "add"), Some(&add().to_value()));
+
+        let prelude = Sandbox::allow_list(vec![add()]).prelude::<DefaultConfig>();
+        assert_eq!(prelude.modules().collect::<Vec<_>>(), vec!["int"]);
+        assert_eq!(prelude.to_value(), V::Map(vec![
+            (V::string("int"), V::Map(vec![(V::string("add"), add().to_value())].into_iter().collect())),
+        ].into_iter().collect()));
+    }
+
+    #[test]
+    fn enforced_while_entered() {
+        let mut vm: VirtualMachine<DefaultV> = VirtualMachine::new();
+
+        {
+            let _guard = Sandbox::deny_list(vec![add()]).enter();
+            match invoke_add(&mut vm) {
+                Err(VvvmFailure::Core(CoreFailure::Disallowed { at })) => assert_eq!(at.function, Some(add())),
+                _ => panic!("expected int_add to be disallowed"),
+            }
+
+            // Entering another sandbox replaces the active one until its guard is dropped.
+            {
+                let _guard = Sandbox::unrestricted().enter();
+                assert_eq!(invoke_add(&mut vm).ok(), Some(V::int(3)));
+            }
+            assert!(invoke_add(&mut vm).is_err());
+        }
+        assert_eq!(invoke_add(&mut vm).ok(), Some(V::int(3)));
+
+        let _guard = Sandbox::no_async().enter();
+        let waker = Waker::from(Arc::new(Noop));
+        let mut cx = Context::from_waker(&waker);
+        let mut f = AsynchronousCoreFunction::PreemptiveYield;
+        let mut fut = BuiltInAsyncFunction::<DefaultV, VvvmFailure<DefaultConfig>, _>::invoke(&mut f, &[], &mut vm);
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(Err(CoreFailure::Disallowed { .. })) => {}
+            _ => panic!("expected preemptive_yield to be disallowed"),
+        }
+    }
+}
@@ -0,0 +1,520 @@
+//! Structured descriptions of the core functions, for generating documentation and editor
+//! support.
+
+use core::fmt;
+use core::fmt::Write;
+
+use crate::fun::{SynchronousCoreFunction, AsynchronousCoreFunction, Parameters};
+use crate::registry::CoreFunction;
+
+/// The type of a parameter or result, as far as the core library checks it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Type {
+    /// Any value whatsoever.
+    Any,
+    /// No value, the function never returns successfully.
+    Never,
+    Nil,
+    Bool,
+    Int,
+    /// An int that is greater than or equal to zero.
+    PositiveInt,
+    /// An int that is greater than zero.
+    StrictlyPositiveInt,
+    /// An int other than zero.
+    NonZeroInt,
+    Float,
+    /// An array of utf-8 bytes, see `V::string`.
+    String,
+    /// An array whose elements have the given type.
+    Array(&'static Type),
+    /// An array with at least one element, of the given type.
+    NonEmptyArray(&'static Type),
+    /// A map with keys and values of the given types.
+    Map(&'static Type, &'static Type),
+    /// An opaque value of the given kind, see `Opaque`.
+    Opaque(&'static str),
+    /// Either an ok value of the first type or an err value of the second one, see `V::ok` and
+    /// `V::err`.
+    Result(&'static Type, &'static Type),
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Any => write!(f, "any"),
+            Type::Never => write!(f, "never"),
+            Type::Nil => write!(f, "nil"),
+            Type::Bool => write!(f, "bool"),
+            Type::Int => write!(f, "int"),
+            Type::PositiveInt => write!(f, "positive_int"),
+            Type::StrictlyPositiveInt => write!(f, "strictly_positive_int"),
+            Type::NonZeroInt => write!(f, "non_zero_int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Array(t) => write!(f, "array<{}>", t),
+            Type::NonEmptyArray(t) => write!(f, "non_empty_array<{}>", t),
+            Type::Map(k, v) => write!(f, "map<{}, {}>", k, v),
+            Type::Opaque(kind) => write!(f, "{}", kind),
+            Type::Result(ok, err) => write!(f, "result<{}, {}>", ok, err),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Param {
+    pub name: &'static str,
+    pub ty: Type,
+}
+
+/// The description of a core function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub name: &'static str,
+    pub module: &'static str,
+    /// One entry per argument, see `parameters` for which of them are optional or packed.
+    pub params: &'static [Param],
+    pub parameters: Parameters,
+    pub result: Type,
+    /// The names of the `CoreFailure` variants the function can fail with. `Disallowed`, which
+    /// every function can fail with inside a `Sandbox`, and `OutOfFuel`, which every synchronous
+    /// function can fail with when metered by `Fuel`, are not listed.
+    pub failures: &'static [&'static str],
+    pub pure: bool,
+    pub asynchronous: bool,
+    pub doc: &'static str,
+}
+
+impl Signature {
+    /// Serialize as a JSON object.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        write!(
+            out,
+            "{{\"name\": {}, \"module\": {}, \"params\": [",
+            json_string(self.name), json_string(self.module),
+        ).unwrap();
+        for (i, param) in self.params.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            write!(
+                out,
+                "{{\"name\": {}, \"type\": {}}}",
+                json_string(param.name), json_string(&param.ty.to_string()),
+            ).unwrap();
+        }
+        write!(
+            out,
+            "], \"required\": {}, \"optional\": {}, \"rest\": {}, \"result\": {}, \"failures\": [",
+            self.parameters.required, self.parameters.optional, self.parameters.rest,
+            json_string(&self.result.to_string()),
+        ).unwrap();
+        for (i, failure) in self.failures.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            out.push_str(&json_string(failure));
+        }
+        write!(
+            out,
+            "], \"pure\": {}, \"asynchronous\": {}, \"doc\": {}}}",
+            self.pure, self.asynchronous, json_string(self.doc),
+        ).unwrap();
+        out
+    }
+}
+
+/// The signatures of all core functions as a JSON array.
+pub fn all_to_json() -> String {
+    let mut out = String::from("[\n");
+    let signatures: Vec<_> = CoreFunction::all().map(|f| f.signature()).collect();
+    for (i, signature) in signatures.iter().enumerate() {
+        out.push_str("  ");
+        out.push_str(&signature.to_json());
+        if i + 1 < signatures.len() {
+            out.push(',');
+        }
+        out.push('\n');
+    }
+    out.push(']');
+    out
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Generates the `signature` method of a core function enum from a table listing the parameters,
+// result type, failures and doc string of each function. Name, module and purity are taken from
+// the `core_functions!` table.
+macro_rules! signatures {
+    ($enum_name:ident, $asynchronous:expr, {
+        $($variant:ident($($param:ident: $ty:expr),*) -> $result:expr, [$($failure:ident),*], $doc:literal;)*
+    }) => (
+        impl $enum_name {
+            pub fn signature(&self) -> Signature {
+                #[allow(unused_imports)]
+                use Type::*;
+
+                let (params, result, failures, doc): (&'static [Param], Type, &'static [&'static str], &'static str) = match self {
+                    $($enum_name::$variant => (
+                        &[$(Param { name: stringify!($param), ty: $ty }),*],
+                        $result,
+                        &[$(stringify!($failure)),*],
+                        $doc,
+                    ),)*
+                };
+
+                Signature {
+                    name: self.name(),
+                    module: self.module(),
+                    params,
+                    parameters: self.parameters(),
+                    result,
+                    failures,
+                    pure: self.is_pure(),
+                    asynchronous: $asynchronous,
+                    doc,
+                }
+            }
+        }
+    );
+}
+
+signatures!(SynchronousCoreFunction, false, {
+    ValueHalt(v: Any) -> Never, [Halt], "Stop execution with a failure carrying `v`.";
+    ValueTypeOf(v: Any) -> String, [], "The name of the type of `v`.";
+    ValueTruthy(v: Any) -> Bool, [], "Whether `v` is truthy, i.e. neither `nil` nor `false`.";
+    ValueFalsey(v: Any) -> Bool, [], "Whether `v` is falsey, i.e. `nil` or `false`.";
+    ValuePretty(v: Any) -> String, [], "A human-readable rendering of `v`.";
+
+    OrderTotalCompare(v: Any, w: Any) -> String, [],
+        "Compare `v` and `w` in the total order on values, yielding `\"<\"`, `\"=\"` or `\">\"`.";
+    OrderTotalLt(v: Any, w: Any) -> Bool, [],
+        "Whether `v` is less than `w` in the total order on values.";
+    OrderTotalLeq(v: Any, w: Any) -> Bool, [],
+        "Whether `v` is less than or equal to `w` in the total order on values.";
+    OrderTotalEq(v: Any, w: Any) -> Bool, [], "Whether `v` is equal to `w`.";
+    OrderTotalGeq(v: Any, w: Any) -> Bool, [],
+        "Whether `v` is greater than or equal to `w` in the total order on values.";
+    OrderTotalGt(v: Any, w: Any) -> Bool, [],
+        "Whether `v` is greater than `w` in the total order on values.";
+    OrderTotalNeq(v: Any, w: Any) -> Bool, [], "Whether `v` is not equal to `w`.";
+    OrderTotalMin(v: Any, vs: Array(&Any)) -> Any, [WrongType],
+        "The least of one or more values in the total order on values.";
+    OrderTotalMax(v: Any, vs: Array(&Any)) -> Any, [WrongType],
+        "The greatest of one or more values in the total order on values.";
+
+    OrderPartialCompare(v: Any, w: Any) -> Result(&String, &Nil), [],
+        "Compare `v` and `w` in the partial order on values, an error if they are incomparable.";
+    OrderPartialLt(v: Any, w: Any) -> Result(&Bool, &Nil), [],
+        "Whether `v` is less than `w` in the partial order on values, an error if they are incomparable.";
+    OrderPartialLeq(v: Any, w: Any) -> Result(&Bool, &Nil), [],
+        "Whether `v` is less than or equal to `w` in the partial order on values, an error if they are incomparable.";
+    OrderPartialEq(v: Any, w: Any) -> Result(&Bool, &Nil), [],
+        "Whether `v` is equal to `w` in the partial order on values, an error if they are incomparable.";
+    OrderPartialGeq(v: Any, w: Any) -> Result(&Bool, &Nil), [],
+        "Whether `v` is greater than or equal to `w` in the partial order on values, an error if they are incomparable.";
+    OrderPartialGt(v: Any, w: Any) -> Result(&Bool, &Nil), [],
+        "Whether `v` is greater than `w` in the partial order on values, an error if they are incomparable.";
+    OrderPartialNeq(v: Any, w: Any) -> Result(&Bool, &Nil), [],
+        "Whether `v` is not equal to `w` in the partial order on values, an error if they are incomparable.";
+    OrderPartialGreatestLowerBound(v: Any, w: Any) -> Result(&Any, &Nil), [],
+        "The greatest lower bound of `v` and `w` in the partial order on values, an error if there is none.";
+    OrderPartialLeastUpperBound(v: Any, w: Any) -> Result(&Any, &Nil), [],
+        "The least upper bound of `v` and `w` in the partial order on values, an error if there is none.";
+
+    BoolNot(b: Bool) -> Bool, [WrongType], "Logical negation.";
+    BoolAnd(b: Bool, c: Bool) -> Bool, [WrongType], "Logical conjunction.";
+    BoolOr(b: Bool, c: Bool) -> Bool, [WrongType], "Logical disjunction.";
+    BoolIf(b: Bool, c: Bool) -> Bool, [WrongType], "Logical implication: `b` implies `c`.";
+    BoolIff(b: Bool, c: Bool) -> Bool, [WrongType], "Logical equivalence.";
+    BoolXor(b: Bool, c: Bool) -> Bool, [WrongType], "Exclusive disjunction.";
+
+    FloatAdd(x: Float, y: Float) -> Float, [WrongType], "Addition.";
+    FloatSub(x: Float, y: Float) -> Float, [WrongType], "Subtraction.";
+    FloatMul(x: Float, y: Float) -> Float, [WrongType], "Multiplication.";
+    FloatDiv(x: Float, y: Float) -> Float, [WrongType], "Division.";
+    FloatMulAdd(x: Float, y: Float, z: Float) -> Float, [WrongType],
+        "Compute `(x * y) + z` with only one rounding error.";
+    FloatNeg(x: Float) -> Float, [WrongType], "Negation.";
+    FloatFloor(x: Float) -> Float, [WrongType],
+        "The largest integral float less than or equal to `x`.";
+    FloatCeil(x: Float) -> Float, [WrongType],
+        "The smallest integral float greater than or equal to `x`.";
+    FloatRound(x: Float, digits: Int) -> Float, [WrongType],
+        "Round `x` to `digits` decimal digits, or to the nearest integral float if `digits` is left out, rounding half-way cases away from zero.";
+    FloatTrunc(x: Float) -> Float, [WrongType], "The integral part of `x`.";
+    FloatFract(x: Float) -> Float, [WrongType], "The fractional part of `x`.";
+    FloatAbs(x: Float) -> Float, [WrongType], "The absolute value of `x`.";
+    FloatSignum(x: Float) -> Float, [WrongType],
+        "`1.0` if `x` is positive, `-1.0` if it is negative, `NaN` if it is `NaN`.";
+    FloatPow(x: Float, y: Float) -> Float, [WrongType], "Raise `x` to the power of `y`.";
+    FloatSqrt(x: Float) -> Float, [WrongType], "The square root of `x`.";
+    FloatExp(x: Float) -> Float, [WrongType], "`e` raised to the power of `x`.";
+    FloatExp2(x: Float) -> Float, [WrongType], "`2` raised to the power of `x`.";
+    FloatLn(x: Float) -> Float, [WrongType], "The natural logarithm of `x`.";
+    FloatLog2(x: Float) -> Float, [WrongType], "The base 2 logarithm of `x`.";
+    FloatLog10(x: Float) -> Float, [WrongType], "The base 10 logarithm of `x`.";
+    FloatHypot(x: Float, y: Float) -> Float, [WrongType],
+        "The length of the hypotenuse of a right-angle triangle with legs of length `x` and `y`.";
+    FloatSin(x: Float) -> Float, [WrongType], "The sine of `x` (in radians).";
+    FloatCos(x: Float) -> Float, [WrongType], "The cosine of `x` (in radians).";
+    FloatTan(x: Float) -> Float, [WrongType], "The tangent of `x` (in radians).";
+    FloatAsin(x: Float) -> Float, [WrongType], "The arcsine of `x`, in radians.";
+    FloatAcos(x: Float) -> Float, [WrongType], "The arccosine of `x`, in radians.";
+    FloatAtan(x: Float) -> Float, [WrongType], "The arctangent of `x`, in radians.";
+    FloatAtan2(x: Float, y: Float) -> Float, [WrongType],
+        "The four quadrant arctangent of `x` and `y`, in radians.";
+    FloatExpM1(x: Float) -> Float, [WrongType],
+        "Compute `e^x - 1`, accurate even if `x` is close to zero.";
+    FloatLn1P(x: Float) -> Float, [WrongType],
+        "Compute `ln(1 + x)`, more accurate than separate operations.";
+    FloatSinh(x: Float) -> Float, [WrongType], "The hyperbolic sine of `x`.";
+    FloatCosh(x: Float) -> Float, [WrongType], "The hyperbolic cosine of `x`.";
+    FloatTanh(x: Float) -> Float, [WrongType], "The hyperbolic tangent of `x`.";
+    FloatAsinh(x: Float) -> Float, [WrongType], "The inverse hyperbolic sine of `x`.";
+    FloatAcosh(x: Float) -> Float, [WrongType], "The inverse hyperbolic cosine of `x`.";
+    FloatAtanh(x: Float) -> Float, [WrongType], "The inverse hyperbolic tangent of `x`.";
+    FloatIsNormal(x: Float) -> Bool, [WrongType],
+        "Whether `x` is neither zero, infinite, subnormal nor `NaN`.";
+    FloatToDegrees(x: Float) -> Float, [WrongType], "Convert radians to degrees.";
+    FloatToRadians(x: Float) -> Float, [WrongType], "Convert degrees to radians.";
+    FloatToInt(xf: Float) -> Result(&Int, &Float), [WrongType],
+        "Truncate `xf` to an integer, an error carrying `xf` if it is out of range or `NaN`.";
+    FloatFromInt(n: Int) -> Float, [WrongType], "The float closest to `n`.";
+    FloatToBits(x: Float) -> Int, [WrongType],
+        "The bit pattern of `x`, reinterpreted as an integer.";
+    FloatFromBits(n: Int) -> Float, [WrongType], "The float whose bit pattern is `n`.";
+
+    IntSignum(n: Int) -> Int, [WrongType],
+        "`1` if `n` is positive, `-1` if it is negative, `0` if it is zero.";
+    IntAdd(n: Int, m: Int) -> Int, [WrongType, Overflow], "Addition, failing on overflow.";
+    IntSub(n: Int, m: Int) -> Int, [WrongType, Overflow], "Subtraction, failing on overflow.";
+    IntMul(n: Int, m: Int) -> Int, [WrongType, Overflow], "Multiplication, failing on overflow.";
+    IntDiv(n: Int, m: NonZeroInt) -> Int, [WrongType, Overflow],
+        "Euclidean division, failing on overflow.";
+    IntDivTrunc(n: Int, m: NonZeroInt) -> Int, [WrongType, Overflow],
+        "Truncating division, failing on overflow.";
+    IntMod(n: Int, m: NonZeroInt) -> Int, [WrongType, Overflow],
+        "The remainder of euclidean division, failing on overflow.";
+    IntModTrunc(n: Int, m: NonZeroInt) -> Int, [WrongType, Overflow],
+        "The remainder of truncating division, failing on overflow.";
+    IntNeg(n: Int) -> Int, [WrongType, Overflow], "Negation, failing on overflow.";
+    IntAbs(n: Int) -> Int, [WrongType, Overflow], "The absolute value, failing on overflow.";
+    IntPow(n: Int, m: PositiveInt) -> Int, [WrongType, Overflow],
+        "Raise `n` to the power of `m`, failing on overflow.";
+
+    IntCheckAdd(n: Int, m: Int) -> Result(&Int, &Nil), [WrongType], "Addition, an error on overflow.";
+    IntCheckSub(n: Int, m: Int) -> Result(&Int, &Nil), [WrongType],
+        "Subtraction, an error on overflow.";
+    IntCheckMul(n: Int, m: Int) -> Result(&Int, &Nil), [WrongType],
+        "Multiplication, an error on overflow.";
+    IntCheckDiv(n: Int, m: NonZeroInt) -> Result(&Int, &Nil), [WrongType],
+        "Euclidean division, an error on overflow.";
+    IntCheckDivTrunc(n: Int, m: NonZeroInt) -> Result(&Int, &Nil), [WrongType],
+        "Truncating division, an error on overflow.";
+    IntCheckMod(n: Int, m: NonZeroInt) -> Result(&Int, &Nil), [WrongType],
+        "The remainder of euclidean division, an error on overflow.";
+    IntCheckModTrunc(n: Int, m: NonZeroInt) -> Result(&Int, &Nil), [WrongType],
+        "The remainder of truncating division, an error on overflow.";
+    IntCheckNeg(n: Int) -> Result(&Int, &Nil), [WrongType], "Negation, an error on overflow.";
+    IntCheckAbs(n: Int) -> Result(&Int, &Nil), [WrongType],
+        "The absolute value, an error on overflow.";
+    IntCheckPow(n: Int, m: PositiveInt) -> Result(&Int, &Nil), [WrongType],
+        "Raise `n` to the power of `m`, an error on overflow.";
+
+    IntSatAdd(n: Int, m: Int) -> Int, [WrongType], "Addition, saturating at the numeric bounds.";
+    IntSatSub(n: Int, m: Int) -> Int, [WrongType], "Subtraction, saturating at the numeric bounds.";
+    IntSatMul(n: Int, m: Int) -> Int, [WrongType], "Multiplication, saturating at the numeric bounds.";
+    IntSatPow(n: Int, m: PositiveInt) -> Int, [WrongType],
+        "Raise `n` to the power of `m`, saturating at the numeric bounds.";
+
+    IntWrapAdd(n: Int, m: Int) -> Int, [WrongType], "Addition, wrapping around at the numeric bounds.";
+    IntWrapSub(n: Int, m: Int) -> Int, [WrongType],
+        "Subtraction, wrapping around at the numeric bounds.";
+    IntWrapMul(n: Int, m: Int) -> Int, [WrongType],
+        "Multiplication, wrapping around at the numeric bounds.";
+    IntWrapDiv(n: Int, m: NonZeroInt) -> Int, [WrongType],
+        "Euclidean division, wrapping around at the numeric bounds.";
+    IntWrapDivTrunc(n: Int, m: NonZeroInt) -> Int, [WrongType],
+        "Truncating division, wrapping around at the numeric bounds.";
+    IntWrapMod(n: Int, m: NonZeroInt) -> Int, [WrongType],
+        "The remainder of euclidean division, wrapping around at the numeric bounds.";
+    IntWrapModTrunc(n: Int, m: NonZeroInt) -> Int, [WrongType],
+        "The remainder of truncating division, wrapping around at the numeric bounds.";
+    IntWrapNeg(n: Int) -> Int, [WrongType], "Negation, wrapping around at the numeric bounds.";
+    IntWrapAbs(n: Int) -> Int, [WrongType],
+        "The absolute value, wrapping around at the numeric bounds.";
+    IntWrapPow(n: Int, m: PositiveInt) -> Int, [WrongType],
+        "Raise `n` to the power of `m`, wrapping around at the numeric bounds.";
+
+    IntBitCountOnes(n: Int) -> Int, [WrongType],
+        "The number of ones in the binary representation of `n`.";
+    IntBitCountZeros(n: Int) -> Int, [WrongType],
+        "The number of zeros in the binary representation of `n`.";
+    IntBitLeadingOnes(n: Int) -> Int, [WrongType],
+        "The number of leading ones in the binary representation of `n`.";
+    IntBitLeadingZeros(n: Int) -> Int, [WrongType],
+        "The number of leading zeros in the binary representation of `n`.";
+    IntBitTrailingOnes(n: Int) -> Int, [WrongType],
+        "The number of trailing ones in the binary representation of `n`.";
+    IntBitTrailingZeros(n: Int) -> Int, [WrongType],
+        "The number of trailing zeros in the binary representation of `n`.";
+    IntBitRotateLeft(n: Int, m: PositiveInt) -> Int, [WrongType],
+        "Rotate the bits of `n` to the left by `m` positions.";
+    IntBitRotateRight(n: Int, m: PositiveInt) -> Int, [WrongType],
+        "Rotate the bits of `n` to the right by `m` positions.";
+    IntBitReverseBytes(n: Int) -> Int, [WrongType], "Reverse the byte order of `n`.";
+    IntBitReverseBits(n: Int) -> Int, [WrongType], "Reverse the bit order of `n`.";
+    IntBitShl(n: Int, m: PositiveInt) -> Int, [WrongType],
+        "Shift the bits of `n` to the left by `m` positions, `0` if `m` is at least 64.";
+    IntBitShr(n: Int, m: PositiveInt) -> Int, [WrongType],
+        "Shift the bits of `n` to the right by `m` positions, `0` if `m` is at least 64.";
+
+    ArrayConcat(arrays: Array(&Array(&Any))) -> Array(&Any), [WrongType], "Concatenate any number of arrays.";
+
+    FunTry(f: Any, args: Array(&Any)) -> Result(&Any, &Map(&String, &Any)), [WrongType, Halt],
+        "Apply `f` to `args`, an error value describing the failure if it fails with anything but a halt.";
+
+    ChannelNew(capacity: StrictlyPositiveInt) -> Opaque("channel"), [WrongType],
+        "A new channel buffering at most `capacity` values, which must not be zero, or unbounded if `capacity` is `nil`.";
+    ChannelTryRecv(channel: Opaque("channel")) -> Result(&Any, &String), [WrongType],
+        "Take the next value out of `channel` without waiting, an error `\"empty\"` or `\"closed\"` if there is none.";
+    ChannelClose(channel: Opaque("channel")) -> Nil, [WrongType],
+        "Close `channel`, waking all waiting tasks. Values sent before closing can still be received.";
+
+    TimeNow() -> PositiveInt, [NoExecutor], "The current time of the executor's clock in milliseconds.";
+
+    SyncMutexNew() -> Opaque("mutex"), [], "A new, unlocked mutex.";
+    SyncMutexUnlock(mutex: Opaque("mutex")) -> Nil, [WrongType, NotLocked],
+        "Unlock `mutex`, letting the task that has waited longest lock it. Fails if it is not locked.";
+    SyncSemaphoreNew(permits: PositiveInt) -> Opaque("semaphore"), [WrongType], "A new semaphore with `permits` permits.";
+    SyncSemaphoreRelease(semaphore: Opaque("semaphore")) -> Nil, [WrongType], "Add a permit to `semaphore`.";
+    SyncBarrierNew(size: StrictlyPositiveInt) -> Opaque("barrier"), [WrongType],
+        "A new barrier that releases waiting tasks in groups of `size`, which must not be zero.";
+    SyncEventNew() -> Opaque("event"), [], "A new one-shot event that has not been set.";
+    SyncEventSet(event: Opaque("event")) -> Bool, [WrongType],
+        "Set `event`, waking all tasks waiting for it. Returns whether it had not been set before.";
+
+    GeneratorNew(f: Any) -> Opaque("generator"), [],
+        "A generator whose values are yielded by `f`, which is applied to the generator once the first value is requested. The array and map functions do not accept generators, turn them into arrays with `generator_collect` or `generator_take` first.";
+    GeneratorClose(generator: Opaque("generator")) -> Nil, [WrongType],
+        "Stop `generator`, cancelling its producer. Requesting further values yields the error `\"done\"`.";
+
+    FuelRemaining() -> Any, [], "The fuel left for the current task, `nil` if it is not metered.";
+});
+
+signatures!(AsynchronousCoreFunction, true, {
+    PreemptiveYield() -> Nil, [], "Suspend the current task so that others can run, resuming with `nil`.";
+    TaskSpawn(f: Any) -> Opaque("task"), [NoExecutor],
+        "Run `f` without arguments as a child of the current task, returning a handle to the new task.";
+    TaskJoin(task: Opaque("task")) -> Result(&Any, &Map(&String, &Any)), [WrongType],
+        "Wait for `task` to finish, an error value describing the failure if it failed or was cancelled.";
+    TaskCancel(task: Opaque("task")) -> Nil, [WrongType],
+        "Cancel `task` and all tasks it spawned, transitively.";
+    TaskJoinAll(tasks: Array(&Opaque("task"))) -> Array(&Result(&Any, &Map(&String, &Any))), [WrongType],
+        "Wait for all `tasks` to finish, returning their outcomes in order as `task_join` would.";
+    TaskSelect(fs: NonEmptyArray(&Any)) -> Array(&Any), [WrongType, NoExecutor],
+        "Run each of `fs` that is not a task handle as a child task, and once the first of the tasks finishes cancel the others and return `[index, outcome]`, the outcome as `task_join` would return it.";
+    ChannelSend(channel: Opaque("channel"), v: Any) -> Result(&Nil, &String), [WrongType],
+        "Put `v` into `channel`, waiting while it is full, an error `\"closed\"` if it is closed.";
+    ChannelRecv(channel: Opaque("channel")) -> Result(&Any, &String), [WrongType],
+        "Take the next value out of `channel`, waiting while it is empty, an error `\"closed\"` once it is closed and empty.";
+    TimeSleep(ms: PositiveInt) -> Nil, [WrongType, NoExecutor], "Suspend the current task for `ms` milliseconds.";
+    TimeTimeout(ms: PositiveInt, f: Any) -> Result(&Any, &Any), [WrongType, NoExecutor],
+        "Run `f` as a child task and return its outcome as `task_join` would, or cancel it and return the error `\"timeout\"` if it takes longer than `ms` milliseconds.";
+    SyncMutexLock(mutex: Opaque("mutex")) -> Nil, [WrongType],
+        "Wait until `mutex` is unlocked and lock it. Tasks acquire it in the order in which they started waiting.";
+    SyncSemaphoreAcquire(semaphore: Opaque("semaphore")) -> Nil, [WrongType],
+        "Wait for a permit of `semaphore` and take it. Tasks acquire permits in the order in which they started waiting.";
+    SyncBarrierWait(barrier: Opaque("barrier")) -> Bool, [WrongType],
+        "Wait until as many tasks as the size of `barrier` are waiting, `true` for exactly one of them.";
+    SyncEventWait(event: Opaque("event")) -> Nil, [WrongType], "Wait until `event` is set.";
+    GeneratorYield(generator: Opaque("generator"), v: Any) -> Nil, [WrongType],
+        "Hand `v` to the consumer of `generator`, resuming once the next value is requested.";
+    GeneratorNext(generator: Opaque("generator")) -> Result(&Any, &Any), [WrongType, NoExecutor],
+        "The next value of `generator`, the error `\"done\"` once its producer has returned, or its failure if it failed.";
+    GeneratorCollect(generator: Opaque("generator")) -> Result(&Array(&Any), &Any), [WrongType, NoExecutor],
+        "All remaining values of `generator` as an array, for use with the array functions, or the failure of its producer.";
+    GeneratorTake(generator: Opaque("generator"), n: PositiveInt) -> Result(&Array(&Any), &Any), [WrongType, NoExecutor],
+        "The next `n` values of `generator` as an array, fewer if its producer returns before yielding them all, or the failure of its producer. Lets the array functions process infinite generators piecewise.";
+    FuelYield(amount: PositiveInt) -> Nil, [WrongType],
+        "Let other tasks run, then wait until at least `amount` fuel is left, i.e. until the host has refueled.";
+});
+
+impl CoreFunction {
+    pub fn signature(&self) -> Signature {
+        match self {
+            CoreFunction::Synchronous(f) => f.signature(),
+            CoreFunction::Asynchronous(f) => f.signature(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Type, json_string, all_to_json};
+    use crate::registry::CoreFunction;
+    use crate::fun::{SynchronousCoreFunction, Parameters};
+
+    #[test]
+    fn signatures_match_the_functions() {
+        for f in CoreFunction::all() {
+            let signature = f.signature();
+            assert_eq!(signature.name, f.name());
+            assert_eq!(signature.module, f.module());
+            assert_eq!(signature.params.len(), f.arity() as usize, "{}", f.name());
+            assert_eq!(signature.pure, f.is_pure());
+            assert_eq!(signature.asynchronous, f.is_asynchronous());
+        }
+    }
+
+    #[test]
+    fn parameters_match_the_params() {
+        for f in CoreFunction::all() {
+            let signature = f.signature();
+            assert_eq!(signature.parameters.arity(), f.arity(), "{}", f.name());
+            if signature.parameters.rest {
+                // The packed rest arguments.
+                assert!(matches!(signature.params.last(), Some(p) if matches!(p.ty, Type::Array(_))), "{}", f.name());
+            }
+        }
+
+        let min = CoreFunction::from(SynchronousCoreFunction::OrderTotalMin).signature();
+        assert_eq!(min.parameters, Parameters::variadic(1));
+        assert!(min.to_json().contains("\"required\": 1, \"optional\": 0, \"rest\": true"));
+        let round = CoreFunction::from(SynchronousCoreFunction::FloatRound).signature();
+        assert_eq!(round.parameters, Parameters::optional(1, 1));
+    }
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(json_string("plain `text`"), "\"plain `text`\"");
+        assert_eq!(json_string("a \"b\"\\c\nd"), "\"a \\\"b\\\"\\\\c\\nd\"");
+        assert_eq!(json_string("\t\u{1}é"), "\"\\u0009\\u0001é\"");
+    }
+
+    #[test]
+    fn json_export() {
+        let add = CoreFunction::from(SynchronousCoreFunction::IntAdd).signature().to_json();
+        assert!(add.starts_with("{\"name\": \"int_add\", \"module\": \"int\", \"params\": [{\"name\": \"n\", \"type\": \"int\"}, "));
+        assert!(add.ends_with("\"pure\": true, \"asynchronous\": false, \"doc\": \"Addition, failing on overflow.\"}"));
+
+        let json = all_to_json();
+        assert!(json.starts_with("[\n") && json.ends_with("\n]"));
+        assert_eq!(json.lines().count(), CoreFunction::all().count() + 2);
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use vvvm_rs::{vvvm, V, DefaultConfig, DefaultV, CoreFailure, FromValue, IntoValue};
+
+#[derive(Clone, Debug, PartialEq, FromValue, IntoValue)]
+struct Point {
+    x: i64,
+    y: i64,
+    label: Option<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq, FromValue, IntoValue)]
+struct Meters(f64);
+
+#[derive(Clone, Debug, PartialEq, FromValue, IntoValue)]
+struct Pair(i64, bool);
+
+#[derive(Clone, Debug, PartialEq, FromValue, IntoValue)]
+struct Unit;
+
+#[derive(Clone, Debug, PartialEq, FromValue, IntoValue)]
+enum Shape {
+    Circle { radius: f64 },
+    Square(f64),
+    Empty,
+}
+
+#[derive(Clone, Debug, PartialEq, FromValue, IntoValue)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+fn round_trip<T>(t: T) -> T where
+    T: FromValue<DefaultConfig> + IntoValue<DefaultConfig>,
+{
+    let v: DefaultV = t.into_value();
+    T::from_value(&v).ok().expect("a value converted from the type")
+}
+
+#[test]
+fn std_types() {
+    assert_eq!(round_trip(true), true);
+    assert_eq!(round_trip(-7i64), -7);
+    assert_eq!(round_trip(1.5f64), 1.5);
+    assert_eq!(round_trip(Some(3i64)), Some(3));
+    assert_eq!(round_trip(None::<i64>), None);
+    assert_eq!(round_trip(vec![1i64, 2, 3]), vec![1, 2, 3]);
+    assert_eq!(round_trip((1i64, false, 0.5f64)), (1, false, 0.5));
+
+    let mut m = BTreeMap::new();
+    m.insert(1i64, vec![true]);
+    assert_eq!(round_trip(m.clone()), m);
+}
+
+#[test]
+fn structs() {
+    let point = Point { x: 1, y: -2, label: None };
+    let v: DefaultV = point.clone().into_value();
+    assert_eq!(v, vvvm!({"x": 1, "y": -2, "label": nil}));
+    assert_eq!(round_trip(point.clone()), point);
+
+    let v: DefaultV = Meters(2.5).into_value();
+    assert_eq!(v, vvvm!(2.5));
+    assert_eq!(round_trip(Pair(3, true)), Pair(3, true));
+    assert_eq!(IntoValue::<DefaultConfig>::into_value(Unit), V::nil());
+    assert_eq!(round_trip(Wrapper { inner: vec![1i64] }), Wrapper { inner: vec![1] });
+}
+
+#[test]
+fn enums() {
+    let v: DefaultV = Shape::Square(1.0).into_value();
+    assert_eq!(v, vvvm!({"Square": 1.0}));
+    assert_eq!(round_trip(Shape::Circle { radius: 0.5 }), Shape::Circle { radius: 0.5 });
+    assert_eq!(round_trip(Shape::Empty), Shape::Empty);
+
+    let v: DefaultV = vvvm!({"Triangle": nil});
+    match Shape::from_value(&v) {
+        Err(CoreFailure::UnknownVariant { .. }) => {}
+        other => panic!("expected an unknown variant, got {:?}", other),
+    }
+}
+
+#[test]
+fn missing_field() {
+    let v: DefaultV = vvvm!({"x": 1, "label": nil});
+    match Point::from_value(&v) {
+        Err(CoreFailure::MissingKey { key, .. }) => assert_eq!(key, V::string("y")),
+        other => panic!("expected a missing key, got {:?}", other),
+    }
+}
+
+#[test]
+fn wrong_field_type() {
+    let v: DefaultV = vvvm!({"x": 1, "y": true, "label": nil});
+    match Point::from_value(&v) {
+        Err(CoreFailure::WrongType { value: V::Bool(true), .. }) => {}
+        other => panic!("expected a wrong type, got {:?}", other),
+    }
+}
@@ -0,0 +1,186 @@
+//! Derive macros for the `FromValue` and `IntoValue` traits of vvvm-rs.
+//!
+//! - structs with named fields map to maps from field names to field values, converting a map
+//!   that lacks the entry for a field fails
+//! - tuple structs with a single field map to the value of that field
+//! - other tuple structs map to arrays
+//! - unit structs map to `nil`
+//! - enums map to tagged values: maps with a single entry from the variant name to the payload,
+//!   where the payload of a variant follows the same rules as the struct cases
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, format_ident};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Generics, Ident};
+
+#[proc_macro_derive(FromValue)]
+pub fn derive_from_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let body = match &input.data {
+        Data::Struct(data) => from_fields(quote!(#name), &data.fields, quote!(v)),
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let variant_name = &variant.ident;
+                let tag = variant_name.to_string();
+                let construct = from_fields(quote!(#name::#variant_name), &variant.fields, quote!(payload));
+                quote! {
+                    if tag == &::vvvm_rs::V::string(#tag) {
+                        return #construct;
+                    }
+                }
+            });
+            quote! {
+                let (tag, payload) = ::vvvm_rs::convert::untag(v)?;
+                #(#arms)*
+                Err(::vvvm_rs::CoreFailure::unknown_variant(v.clone()))
+            }
+        }
+        Data::Union(_) => panic!("FromValue can not be derived for unions"),
+    };
+
+    let (impl_generics, where_clause) = generics(&input.generics, quote!(::vvvm_rs::FromValue));
+    let ty_generics = input.generics.split_for_impl().1;
+
+    let expanded = quote! {
+        impl #impl_generics ::vvvm_rs::FromValue<__C> for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn from_value(v: &::vvvm_rs::V<__C>) -> ::core::result::Result<Self, ::vvvm_rs::CoreFailure<::vvvm_rs::V<__C>>> {
+                #body
+            }
+        }
+    };
+    expanded.into()
+}
+
+#[proc_macro_derive(IntoValue)]
+pub fn derive_into_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, value) = into_fields(quote!(#name), &data.fields);
+            quote! {
+                let #pattern = self;
+                #value
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let variant_name = &variant.ident;
+                let tag = variant_name.to_string();
+                let (pattern, value) = into_fields(quote!(#name::#variant_name), &variant.fields);
+                quote! {
+                    #pattern => ::vvvm_rs::convert::tag(#tag, #value),
+                }
+            });
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => panic!("IntoValue can not be derived for unions"),
+    };
+
+    let (impl_generics, where_clause) = generics(&input.generics, quote!(::vvvm_rs::IntoValue));
+    let ty_generics = input.generics.split_for_impl().1;
+
+    let expanded = quote! {
+        impl #impl_generics ::vvvm_rs::IntoValue<__C> for #name #ty_generics #where_clause {
+            fn into_value(self) -> ::vvvm_rs::V<__C> {
+                #body
+            }
+        }
+    };
+    expanded.into()
+}
+
+// Generic parameters and where clause of the generated impl: the configuration parameter, plus the
+// parameters of the type itself, each of which has to implement the derived trait.
+fn generics(generics: &Generics, bound: TokenStream2) -> (TokenStream2, TokenStream2) {
+    let params = generics.params.iter();
+    let type_params: Vec<&Ident> = generics.type_params().map(|param| &param.ident).collect();
+    let predicates = generics.where_clause.as_ref().map(|clause| {
+        let predicates = clause.predicates.iter();
+        quote!(#(#predicates,)*)
+    });
+
+    (
+        quote!(<#(#params,)* __C: ::vvvm_rs::VvvmConfig>),
+        quote! {
+            where
+                #(#type_params: #bound<__C>,)*
+                #predicates
+        },
+    )
+}
+
+// An expression of type `Result<Self, _>` that converts the value `source` into the struct or
+// variant `path`.
+fn from_fields(path: TokenStream2, fields: &Fields, source: TokenStream2) -> TokenStream2 {
+    match fields {
+        Fields::Named(fields) => {
+            let inits = fields.named.iter().map(|field| {
+                let ident = field.ident.as_ref().unwrap();
+                let key = ident.to_string();
+                quote!(#ident: ::vvvm_rs::convert::field(#source, #key)?)
+            });
+            quote! {{
+                ::vvvm_rs::convert::as_map(#source)?;
+                Ok(#path { #(#inits,)* })
+            }}
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            quote!(Ok(#path(::vvvm_rs::FromValue::from_value(#source)?)))
+        }
+        Fields::Unnamed(fields) => {
+            let len = fields.unnamed.len();
+            let inits = (0..len).map(|i| quote!(::vvvm_rs::FromValue::from_value(elements[#i])?));
+            quote! {{
+                let elements = ::vvvm_rs::convert::elements(#source, #len)?;
+                Ok(#path(#(#inits,)*))
+            }}
+        }
+        Fields::Unit => quote!(Ok(#path)),
+    }
+}
+
+// A pattern binding all fields of the struct or variant `path`, and an expression converting the
+// bound fields into a value.
+fn into_fields(path: TokenStream2, fields: &Fields) -> (TokenStream2, TokenStream2) {
+    match fields {
+        Fields::Named(fields) => {
+            let idents: Vec<&Ident> = fields.named.iter().map(|field| field.ident.as_ref().unwrap()).collect();
+            let keys = idents.iter().map(|ident| ident.to_string());
+            (
+                quote!(#path { #(#idents,)* }),
+                quote! {
+                    ::vvvm_rs::V::Map(vec![
+                        #((::vvvm_rs::V::string(#keys), ::vvvm_rs::IntoValue::into_value(#idents)),)*
+                    ].into_iter().collect())
+                },
+            )
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => (
+            quote!(#path(field)),
+            quote!(::vvvm_rs::IntoValue::into_value(field)),
+        ),
+        Fields::Unnamed(fields) => {
+            let idents: Vec<Ident> = (0..fields.unnamed.len()).map(|i| format_ident!("field{}", i)).collect();
+            (
+                quote!(#path(#(#idents,)*)),
+                quote! {
+                    ::vvvm_rs::V::Array(vec![
+                        #(::vvvm_rs::IntoValue::into_value(#idents),)*
+                    ].into_iter().collect())
+                },
+            )
+        }
+        Fields::Unit => (quote!(#path), quote!(::vvvm_rs::V::nil())),
+    }
+}